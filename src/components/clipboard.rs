@@ -0,0 +1,30 @@
+#[cfg(not(feature = "desktop"))]
+use dioxus::prelude::document;
+
+/// 读取系统剪贴板中的文本
+#[cfg(feature = "desktop")]
+pub async fn read_clipboard() -> Option<String> {
+    arboard::Clipboard::new().ok()?.get_text().ok()
+}
+
+/// 写入系统剪贴板
+#[cfg(feature = "desktop")]
+pub async fn write_clipboard(text: String) {
+    if let Ok(mut clipboard) = arboard::Clipboard::new() {
+        let _ = clipboard.set_text(text);
+    }
+}
+
+/// 通过 Clipboard API 读取剪贴板中的文本
+#[cfg(not(feature = "desktop"))]
+pub async fn read_clipboard() -> Option<String> {
+    let mut eval = document::eval("dioxus.send(await navigator.clipboard.readText());");
+    eval.recv::<String>().await.ok()
+}
+
+/// 通过 Clipboard API 写入剪贴板
+#[cfg(not(feature = "desktop"))]
+pub async fn write_clipboard(text: String) {
+    let eval = document::eval("await navigator.clipboard.writeText(await dioxus.recv());");
+    let _ = eval.send(text);
+}
@@ -0,0 +1,740 @@
+use super::editor_new::Editor;
+use super::line_ending::LineEnding;
+use super::position::Position;
+use std::fmt;
+use std::str::FromStr;
+
+/// 光标移动的方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    WordLeft,
+    WordRight,
+    /// 行首(先到第一个非空白字符)
+    LineStart,
+    /// 行的第 0 列
+    LineBegin,
+    LineEnd,
+    DocumentStart,
+    DocumentEnd,
+    /// 翻页，参数为一页的行数，0 表示当前可见的一页(由界面换算，
+    /// 直接执行时为 `DEFAULT_PAGE_LINES` 行)
+    PageUp(usize),
+    PageDown(usize),
+}
+
+/// 没有界面换算时一页的行数
+const DEFAULT_PAGE_LINES: usize = 20;
+
+/// 与界面无关的编辑命令，按键、鼠标和脚本都通过 `Editor::execute` 执行
+#[allow(unused)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditorCommand {
+    /// 移动光标，select 为 true 时扩展选区
+    Move {
+        motion: Motion,
+        select: bool,
+    },
+    SetCursor(Position),
+    SelectTo(Position),
+    AddCursor(Position),
+    AddCursorAbove,
+    AddCursorBelow,
+    AddNextOccurrence,
+    ClearSecondaryCursors,
+    SelectAll,
+    /// 键入单个字符(处理成对字符的自动补全)
+    TypeChar(char),
+    InsertText(String),
+    Paste(String),
+    Copy,
+    Cut,
+    Newline,
+    Backspace,
+    Delete,
+    DeleteWordBackward,
+    DeleteWordForward,
+    Indent,
+    Dedent,
+    DuplicateLines,
+    DeleteLines,
+    MoveLinesUp,
+    MoveLinesDown,
+    JoinLines,
+    ToggleLineComment,
+    ToggleBlockComment,
+    JumpToMatchingBracket,
+    Fold,
+    Unfold,
+    FoldAll,
+    UnfoldAll,
+    Undo,
+    Redo,
+    /// 转换整个文档的换行符
+    SetLineEnding(LineEnding),
+    /// 按 Vim 的动作按键(如 `3w`、`f,`)或可视模式按键序列(如 `vjo$`，到达选区的活动端)
+    /// 从主光标处移动，Vim 方案借此把移动以相对光标的形式录入宏
+    VimMotion(String),
+    /// 选中 Vim 操作符(如 `d2w`、`cc`、`3x`)或可视模式按键序列加操作符(如 `vjd`)
+    /// 从主光标处作用的区间
+    VimSelect(String),
+}
+
+impl Editor {
+    /// 执行一条命令；复制和剪切返回需要写入剪贴板的文本
+    pub fn execute(&mut self, command: EditorCommand) -> Option<String> {
+        self.log_command(&command);
+        match command {
+            EditorCommand::Move { motion, select } => match motion {
+                Motion::Left => self.move_cursor_left(select),
+                Motion::Right => self.move_cursor_right(select),
+                Motion::Up => self.move_cursor_up(select),
+                Motion::Down => self.move_cursor_down(select),
+                Motion::WordLeft => self.move_cursor_word_left(select),
+                Motion::WordRight => self.move_cursor_word_right(select),
+                Motion::LineStart => self.move_cursor_home(select),
+                Motion::LineBegin => self.move_cursor_line_begin(select),
+                Motion::LineEnd => self.move_cursor_end(select),
+                Motion::DocumentStart => self.move_cursor_document_start(select),
+                Motion::DocumentEnd => self.move_cursor_document_end(select),
+                Motion::PageUp(lines) => self.move_cursor_page_up(page_lines(lines), select),
+                Motion::PageDown(lines) => self.move_cursor_page_down(page_lines(lines), select),
+            },
+            EditorCommand::SetCursor(pos) => self.set_cursor_position(pos),
+            EditorCommand::SelectTo(pos) => self.select_to(pos),
+            EditorCommand::AddCursor(pos) => self.add_cursor(pos),
+            EditorCommand::AddCursorAbove => self.add_cursor_above(),
+            EditorCommand::AddCursorBelow => self.add_cursor_below(),
+            EditorCommand::AddNextOccurrence => self.add_next_occurrence(),
+            EditorCommand::ClearSecondaryCursors => self.clear_secondary_cursors(),
+            EditorCommand::SelectAll => self.select_all(),
+            EditorCommand::TypeChar(ch) => self.type_char(ch),
+            EditorCommand::InsertText(text) => self.insert_text(&text),
+            EditorCommand::Paste(text) => self.paste_text(&text),
+            EditorCommand::Copy => return Some(self.copy_text()),
+            EditorCommand::Cut => return Some(self.cut_text()),
+            EditorCommand::Newline => self.move_cursor_enter(),
+            EditorCommand::Backspace => self.move_cursor_backspace(1),
+            EditorCommand::Delete => self.move_cursor_delete(1),
+            EditorCommand::DeleteWordBackward => self.delete_word_backward(),
+            EditorCommand::DeleteWordForward => self.delete_word_forward(),
+            EditorCommand::Indent => self.move_cursor_tab(),
+            EditorCommand::Dedent => self.dedent_lines(),
+            EditorCommand::DuplicateLines => self.duplicate_lines(),
+            EditorCommand::DeleteLines => self.delete_lines(),
+            EditorCommand::MoveLinesUp => self.move_lines_up(),
+            EditorCommand::MoveLinesDown => self.move_lines_down(),
+            EditorCommand::JoinLines => self.join_lines(),
+            EditorCommand::ToggleLineComment => self.toggle_line_comment(),
+            EditorCommand::ToggleBlockComment => self.toggle_block_comment(),
+            EditorCommand::JumpToMatchingBracket => self.jump_to_matching_bracket(),
+            EditorCommand::Fold => self.fold_at_cursor(),
+            EditorCommand::Unfold => self.unfold_at_cursor(),
+            EditorCommand::FoldAll => self.fold_all(),
+            EditorCommand::UnfoldAll => self.unfold_all(),
+            EditorCommand::Undo => self.undo(),
+            EditorCommand::Redo => self.redo(),
+            EditorCommand::SetLineEnding(ending) => self.set_line_ending(ending),
+            EditorCommand::VimMotion(keys) => self.vim_motion(&keys),
+            EditorCommand::VimSelect(keys) => self.vim_select(&keys),
+        }
+        None
+    }
+}
+
+fn page_lines(lines: usize) -> usize {
+    if lines == 0 {
+        DEFAULT_PAGE_LINES
+    } else {
+        lines
+    }
+}
+
+/// 无参数的命令，按名称查找时使用
+pub const SIMPLE_COMMANDS: &[EditorCommand] = &[
+    EditorCommand::AddCursorAbove,
+    EditorCommand::AddCursorBelow,
+    EditorCommand::AddNextOccurrence,
+    EditorCommand::ClearSecondaryCursors,
+    EditorCommand::SelectAll,
+    EditorCommand::Copy,
+    EditorCommand::Cut,
+    EditorCommand::Newline,
+    EditorCommand::Backspace,
+    EditorCommand::Delete,
+    EditorCommand::DeleteWordBackward,
+    EditorCommand::DeleteWordForward,
+    EditorCommand::Indent,
+    EditorCommand::Dedent,
+    EditorCommand::DuplicateLines,
+    EditorCommand::DeleteLines,
+    EditorCommand::MoveLinesUp,
+    EditorCommand::MoveLinesDown,
+    EditorCommand::JoinLines,
+    EditorCommand::ToggleLineComment,
+    EditorCommand::ToggleBlockComment,
+    EditorCommand::JumpToMatchingBracket,
+    EditorCommand::Fold,
+    EditorCommand::Unfold,
+    EditorCommand::FoldAll,
+    EditorCommand::UnfoldAll,
+    EditorCommand::Undo,
+    EditorCommand::Redo,
+];
+
+/// 命令文本无法解析
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseCommandError(pub String);
+
+impl fmt::Display for ParseCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "无法解析的命令：{}", self.0)
+    }
+}
+
+impl std::error::Error for ParseCommandError {}
+
+impl Motion {
+    /// 全部移动方式，翻页为可见的一页
+    pub const ALL: [Motion; 13] = [
+        Motion::Left,
+        Motion::Right,
+        Motion::Up,
+        Motion::Down,
+        Motion::WordLeft,
+        Motion::WordRight,
+        Motion::LineStart,
+        Motion::LineBegin,
+        Motion::LineEnd,
+        Motion::DocumentStart,
+        Motion::DocumentEnd,
+        Motion::PageUp(0),
+        Motion::PageDown(0),
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Motion::Left => "left",
+            Motion::Right => "right",
+            Motion::Up => "up",
+            Motion::Down => "down",
+            Motion::WordLeft => "word_left",
+            Motion::WordRight => "word_right",
+            Motion::LineStart => "line_start",
+            Motion::LineBegin => "line_begin",
+            Motion::LineEnd => "line_end",
+            Motion::DocumentStart => "document_start",
+            Motion::DocumentEnd => "document_end",
+            Motion::PageUp(_) => "page_up",
+            Motion::PageDown(_) => "page_down",
+        }
+    }
+
+    /// 命令面板中显示的名称
+    pub fn title(&self) -> &'static str {
+        match self {
+            Motion::Left => "左移一个字符",
+            Motion::Right => "右移一个字符",
+            Motion::Up => "上移一行",
+            Motion::Down => "下移一行",
+            Motion::WordLeft => "上一个单词",
+            Motion::WordRight => "下一个单词",
+            Motion::LineStart => "行首(第一个非空白字符)",
+            Motion::LineBegin => "行的第 0 列",
+            Motion::LineEnd => "行尾",
+            Motion::DocumentStart => "文档开头",
+            Motion::DocumentEnd => "文档末尾",
+            Motion::PageUp(_) => "上一页",
+            Motion::PageDown(_) => "下一页",
+        }
+    }
+}
+
+/// 文本形式为 `名称` 或 `名称 参数`，如 `page_down 20`
+impl fmt::Display for Motion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Motion::PageUp(lines) | Motion::PageDown(lines) => {
+                write!(f, "{} {}", self.name(), lines)
+            }
+            _ => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+impl FromStr for Motion {
+    type Err = ParseCommandError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseCommandError(s.to_string());
+        let (name, arg) = split_argument(s);
+        // 不带行数时为 0，由按键绑定换算为一页
+        let lines = || match arg {
+            Some(arg) => arg.trim().parse().map_err(|_| err()),
+            None => Ok(0),
+        };
+        Ok(match name {
+            "left" => Motion::Left,
+            "right" => Motion::Right,
+            "up" => Motion::Up,
+            "down" => Motion::Down,
+            "word_left" => Motion::WordLeft,
+            "word_right" => Motion::WordRight,
+            "line_start" => Motion::LineStart,
+            "line_begin" => Motion::LineBegin,
+            "line_end" => Motion::LineEnd,
+            "document_start" => Motion::DocumentStart,
+            "document_end" => Motion::DocumentEnd,
+            "page_up" => Motion::PageUp(lines()?),
+            "page_down" => Motion::PageDown(lines()?),
+            _ => return Err(err()),
+        })
+    }
+}
+
+impl EditorCommand {
+    /// 命令名称(snake_case)，移动命令按是否扩展选区分为 move 和 select
+    pub fn name(&self) -> &'static str {
+        match self {
+            EditorCommand::Move { select: false, .. } => "move",
+            EditorCommand::Move { select: true, .. } => "select",
+            EditorCommand::SetCursor(_) => "set_cursor",
+            EditorCommand::SelectTo(_) => "select_to",
+            EditorCommand::AddCursor(_) => "add_cursor",
+            EditorCommand::AddCursorAbove => "add_cursor_above",
+            EditorCommand::AddCursorBelow => "add_cursor_below",
+            EditorCommand::AddNextOccurrence => "add_next_occurrence",
+            EditorCommand::ClearSecondaryCursors => "clear_secondary_cursors",
+            EditorCommand::SelectAll => "select_all",
+            EditorCommand::TypeChar(_) => "type_char",
+            EditorCommand::InsertText(_) => "insert_text",
+            EditorCommand::Paste(_) => "paste",
+            EditorCommand::Copy => "copy",
+            EditorCommand::Cut => "cut",
+            EditorCommand::Newline => "newline",
+            EditorCommand::Backspace => "backspace",
+            EditorCommand::Delete => "delete",
+            EditorCommand::DeleteWordBackward => "delete_word_backward",
+            EditorCommand::DeleteWordForward => "delete_word_forward",
+            EditorCommand::Indent => "indent",
+            EditorCommand::Dedent => "dedent",
+            EditorCommand::DuplicateLines => "duplicate_lines",
+            EditorCommand::DeleteLines => "delete_lines",
+            EditorCommand::MoveLinesUp => "move_lines_up",
+            EditorCommand::MoveLinesDown => "move_lines_down",
+            EditorCommand::JoinLines => "join_lines",
+            EditorCommand::ToggleLineComment => "toggle_line_comment",
+            EditorCommand::ToggleBlockComment => "toggle_block_comment",
+            EditorCommand::JumpToMatchingBracket => "jump_to_matching_bracket",
+            EditorCommand::Fold => "fold",
+            EditorCommand::Unfold => "unfold",
+            EditorCommand::FoldAll => "fold_all",
+            EditorCommand::UnfoldAll => "unfold_all",
+            EditorCommand::Undo => "undo",
+            EditorCommand::Redo => "redo",
+            EditorCommand::SetLineEnding(_) => "set_line_ending",
+            EditorCommand::VimMotion(_) => "vim_motion",
+            EditorCommand::VimSelect(_) => "vim_select",
+        }
+    }
+
+    /// 命令面板中显示的名称
+    pub fn title(&self) -> &'static str {
+        match self {
+            EditorCommand::Move { select: false, .. } => "移动光标",
+            EditorCommand::Move { select: true, .. } => "扩展选区",
+            EditorCommand::SetCursor(_) => "设置光标",
+            EditorCommand::SelectTo(_) => "选择到",
+            EditorCommand::AddCursor(_) => "添加光标",
+            EditorCommand::AddCursorAbove => "在上一行添加光标",
+            EditorCommand::AddCursorBelow => "在下一行添加光标",
+            EditorCommand::AddNextOccurrence => "选择下一个相同内容",
+            EditorCommand::ClearSecondaryCursors => "只保留主光标",
+            EditorCommand::SelectAll => "全选",
+            EditorCommand::TypeChar(_) => "输入字符",
+            EditorCommand::InsertText(_) => "插入文本",
+            EditorCommand::Paste(_) => "粘贴文本",
+            EditorCommand::Copy => "复制",
+            EditorCommand::Cut => "剪切",
+            EditorCommand::Newline => "换行",
+            EditorCommand::Backspace => "向前删除",
+            EditorCommand::Delete => "向后删除",
+            EditorCommand::DeleteWordBackward => "向前删除单词",
+            EditorCommand::DeleteWordForward => "向后删除单词",
+            EditorCommand::Indent => "缩进",
+            EditorCommand::Dedent => "减少缩进",
+            EditorCommand::DuplicateLines => "复制行",
+            EditorCommand::DeleteLines => "删除行",
+            EditorCommand::MoveLinesUp => "上移行",
+            EditorCommand::MoveLinesDown => "下移行",
+            EditorCommand::JoinLines => "合并行",
+            EditorCommand::ToggleLineComment => "切换行注释",
+            EditorCommand::ToggleBlockComment => "切换块注释",
+            EditorCommand::JumpToMatchingBracket => "跳转到匹配的括号",
+            EditorCommand::Fold => "折叠",
+            EditorCommand::Unfold => "展开",
+            EditorCommand::FoldAll => "折叠全部",
+            EditorCommand::UnfoldAll => "展开全部",
+            EditorCommand::Undo => "撤销",
+            EditorCommand::Redo => "重做",
+            EditorCommand::SetLineEnding(_) => "转换换行符",
+            EditorCommand::VimMotion(_) => "Vim 移动",
+            EditorCommand::VimSelect(_) => "Vim 选择",
+        }
+    }
+}
+
+/// 单行文本形式，如 `select word_left`、`set_cursor 3:4`、`insert_text a\nb`，
+/// 文本参数中的反斜杠、换行和制表符会被转义
+impl fmt::Display for EditorCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EditorCommand::Move { motion, .. } => write!(f, "{} {}", self.name(), motion),
+            EditorCommand::SetCursor(pos)
+            | EditorCommand::SelectTo(pos)
+            | EditorCommand::AddCursor(pos) => {
+                write!(f, "{} {}:{}", self.name(), pos.line, pos.col)
+            }
+            EditorCommand::TypeChar(ch) => {
+                write!(f, "{} {}", self.name(), escape(&ch.to_string()))
+            }
+            EditorCommand::InsertText(text)
+            | EditorCommand::Paste(text)
+            | EditorCommand::VimMotion(text)
+            | EditorCommand::VimSelect(text) => write!(f, "{} {}", self.name(), escape(text)),
+            EditorCommand::SetLineEnding(ending) => write!(f, "{} {}", self.name(), ending.name()),
+            _ => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+impl FromStr for EditorCommand {
+    type Err = ParseCommandError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseCommandError(s.to_string());
+        let (name, arg) = split_argument(s);
+        let position = || {
+            let (line, col) = arg.ok_or_else(err)?.split_once(':').ok_or_else(err)?;
+            match (line.trim().parse(), col.trim().parse()) {
+                (Ok(line), Ok(col)) => Ok(Position::new(line, col)),
+                _ => Err(err()),
+            }
+        };
+        let text = || arg.map(unescape).ok_or_else(err);
+        Ok(match name {
+            "move" | "select" => EditorCommand::Move {
+                motion: arg.ok_or_else(err)?.parse()?,
+                select: name == "select",
+            },
+            "set_cursor" => EditorCommand::SetCursor(position()?),
+            "select_to" => EditorCommand::SelectTo(position()?),
+            "add_cursor" => EditorCommand::AddCursor(position()?),
+            "type_char" => {
+                let text = text()?;
+                let mut chars = text.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(ch), None) => EditorCommand::TypeChar(ch),
+                    _ => return Err(err()),
+                }
+            }
+            "insert_text" => EditorCommand::InsertText(text()?),
+            "paste" => EditorCommand::Paste(text()?),
+            "vim_motion" => EditorCommand::VimMotion(text()?),
+            "vim_select" => EditorCommand::VimSelect(text()?),
+            "set_line_ending" => {
+                EditorCommand::SetLineEnding(arg.and_then(LineEnding::from_name).ok_or_else(err)?)
+            }
+            _ => SIMPLE_COMMANDS
+                .iter()
+                .find(|command| command.name() == name)
+                .cloned()
+                .ok_or_else(err)?,
+        })
+    }
+}
+
+/// 拆分出名称和其后的参数(参数保留原样，不去除空白)
+fn split_argument(s: &str) -> (&str, Option<&str>) {
+    match s.split_once(' ') {
+        Some((name, arg)) => (name, Some(arg)),
+        None => (s, None),
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for ch in text.chars() {
+        match ch {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            unescaped.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('t') => unescaped.push('\t'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(editor: &mut Editor, commands: Vec<EditorCommand>) {
+        for command in commands {
+            editor.execute(command);
+        }
+    }
+
+    fn go(motion: Motion) -> EditorCommand {
+        EditorCommand::Move {
+            motion,
+            select: false,
+        }
+    }
+
+    fn cursor(editor: &Editor) -> (usize, usize) {
+        let pos = editor.get_cursor_position();
+        (pos.line, pos.col)
+    }
+
+    #[test]
+    fn insert_and_type() {
+        let mut editor = Editor::new();
+        run(
+            &mut editor,
+            vec![
+                EditorCommand::InsertText("fn main".to_string()),
+                EditorCommand::TypeChar('('),
+                EditorCommand::TypeChar(')'),
+            ],
+        );
+        assert_eq!(editor.get_text(), "fn main()");
+        assert_eq!(cursor(&editor), (0, 9));
+        run(
+            &mut editor,
+            vec![
+                EditorCommand::TypeChar(' '),
+                EditorCommand::TypeChar('{'),
+                EditorCommand::Newline,
+                EditorCommand::InsertText("x".to_string()),
+            ],
+        );
+        assert_eq!(editor.get_text(), "fn main() {\n    x\n}");
+    }
+
+    #[test]
+    fn delete() {
+        let mut editor = Editor::with_text("hello world");
+        run(
+            &mut editor,
+            vec![
+                go(Motion::LineEnd),
+                EditorCommand::Backspace,
+                EditorCommand::Backspace,
+            ],
+        );
+        assert_eq!(editor.get_text(), "hello wor");
+        run(
+            &mut editor,
+            vec![go(Motion::DocumentStart), EditorCommand::Delete],
+        );
+        assert_eq!(editor.get_text(), "ello wor");
+        run(&mut editor, vec![EditorCommand::DeleteWordForward]);
+        assert_eq!(editor.get_text(), " wor");
+        run(
+            &mut editor,
+            vec![go(Motion::LineEnd), EditorCommand::DeleteWordBackward],
+        );
+        assert_eq!(editor.get_text(), " ");
+    }
+
+    #[test]
+    fn motions() {
+        let mut editor = Editor::with_text("one two\n  three\nfour");
+        run(&mut editor, vec![go(Motion::WordRight)]);
+        assert_eq!(cursor(&editor), (0, 3));
+        run(&mut editor, vec![go(Motion::Down)]);
+        assert_eq!(cursor(&editor), (1, 3));
+        run(&mut editor, vec![go(Motion::LineStart)]);
+        assert_eq!(cursor(&editor), (1, 2));
+        run(&mut editor, vec![go(Motion::LineStart)]);
+        assert_eq!(cursor(&editor), (1, 0));
+        run(
+            &mut editor,
+            vec![go(Motion::LineEnd), go(Motion::LineBegin)],
+        );
+        assert_eq!(cursor(&editor), (1, 0));
+        run(&mut editor, vec![go(Motion::Left)]);
+        assert_eq!(cursor(&editor), (0, 7));
+        run(&mut editor, vec![go(Motion::DocumentEnd)]);
+        assert_eq!(cursor(&editor), (2, 4));
+        run(&mut editor, vec![go(Motion::PageUp(5))]);
+        assert_eq!(cursor(&editor), (0, 4));
+        run(&mut editor, vec![go(Motion::PageDown(1))]);
+        assert_eq!(cursor(&editor), (1, 4));
+    }
+
+    #[test]
+    fn selections() {
+        let mut editor = Editor::with_text("alpha beta\nalpha gamma");
+        run(
+            &mut editor,
+            vec![EditorCommand::Move {
+                motion: Motion::WordRight,
+                select: true,
+            }],
+        );
+        assert_eq!(editor.get_selected_text(), "alpha");
+        run(&mut editor, vec![EditorCommand::AddNextOccurrence]);
+        assert_eq!(editor.get_selections().len(), 2);
+        run(
+            &mut editor,
+            vec![EditorCommand::InsertText("omega".to_string())],
+        );
+        assert_eq!(editor.get_text(), "omega beta\nomega gamma");
+        run(
+            &mut editor,
+            vec![
+                EditorCommand::ClearSecondaryCursors,
+                EditorCommand::SelectAll,
+            ],
+        );
+        assert_eq!(
+            editor.execute(EditorCommand::Cut).as_deref(),
+            Some("omega beta\nomega gamma")
+        );
+        assert_eq!(editor.get_text(), "");
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut editor = Editor::with_text("a\nb");
+        run(
+            &mut editor,
+            vec![
+                go(Motion::LineEnd),
+                EditorCommand::InsertText("x".to_string()),
+                EditorCommand::DeleteLines,
+            ],
+        );
+        assert_eq!(editor.get_text(), "b");
+        run(&mut editor, vec![EditorCommand::Undo]);
+        assert_eq!(editor.get_text(), "ax\nb");
+        run(&mut editor, vec![EditorCommand::Undo]);
+        assert_eq!(editor.get_text(), "a\nb");
+        run(&mut editor, vec![EditorCommand::Redo, EditorCommand::Redo]);
+        assert_eq!(editor.get_text(), "b");
+    }
+
+    #[test]
+    fn page_size_defaults_when_zero() {
+        let text = vec!["x"; DEFAULT_PAGE_LINES * 2].join("\n");
+        let mut editor = Editor::with_text(&text);
+        run(&mut editor, vec![go(Motion::PageDown(0))]);
+        assert_eq!(cursor(&editor), (DEFAULT_PAGE_LINES, 0));
+    }
+
+    #[test]
+    fn out_of_range_positions_are_clamped() {
+        let mut editor = Editor::with_text("ab\ncd");
+        run(
+            &mut editor,
+            vec![EditorCommand::SetCursor(Position::new(9, 9))],
+        );
+        assert_eq!(cursor(&editor), (1, 2));
+        run(
+            &mut editor,
+            vec![
+                EditorCommand::SetCursor(Position::new(0, 1)),
+                EditorCommand::SelectTo(Position::new(0, 99)),
+            ],
+        );
+        assert_eq!(editor.get_selected_text(), "b");
+        run(
+            &mut editor,
+            vec![EditorCommand::AddCursor(Position::new(99, 0))],
+        );
+        assert_eq!(cursor(&editor), (1, 0));
+    }
+
+    #[test]
+    fn commands_round_trip_as_text() {
+        let commands = [
+            EditorCommand::Move {
+                motion: Motion::PageDown(12),
+                select: true,
+            },
+            EditorCommand::SetCursor(Position::new(3, 4)),
+            EditorCommand::TypeChar('\t'),
+            EditorCommand::InsertText("a\\b\r\nc".to_string()),
+            EditorCommand::ToggleLineComment,
+        ];
+        for command in commands {
+            assert_eq!(command.to_string().parse(), Ok(command));
+        }
+        assert!("move sideways".parse::<EditorCommand>().is_err());
+    }
+
+    #[test]
+    fn inserted_line_endings_are_normalized() {
+        let mut editor = Editor::new();
+        run(
+            &mut editor,
+            vec![
+                EditorCommand::InsertText("a\r\nb\rc".to_string()),
+                EditorCommand::Paste("\r\nd".to_string()),
+            ],
+        );
+        assert_eq!(editor.get_text(), "a\nb\nc\nd");
+    }
+
+    #[test]
+    fn set_line_ending_is_a_save_time_setting() {
+        let mut editor = Editor::with_text("a\r\nb\nc");
+        assert_eq!(editor.line_ending(), LineEnding::Lf);
+        assert!(editor.has_mixed_line_endings());
+        editor.execute(EditorCommand::SetLineEnding(LineEnding::Crlf));
+        assert_eq!(editor.line_ending(), LineEnding::Crlf);
+        assert!(!editor.has_mixed_line_endings());
+        // 文本不变，也不进入撤销历史
+        assert_eq!(editor.get_text(), "a\nb\nc");
+        editor.execute(EditorCommand::Undo);
+        assert_eq!(editor.line_ending(), LineEnding::Crlf);
+    }
+
+    #[test]
+    fn fold_and_unfold_all() {
+        let mut editor = Editor::with_text("fn a() {\n    1\n}\nfn b() {\n    2\n}\n");
+        editor.execute(EditorCommand::SetCursor(Position::new(4, 4)));
+        editor.execute(EditorCommand::FoldAll);
+        let hidden = editor.hidden_lines(&editor.fold_regions());
+        assert_eq!(hidden, [1..2, 4..5]);
+        // 被隐藏的光标移到首行末尾
+        assert_eq!(cursor(&editor), (3, 8));
+        editor.execute(EditorCommand::UnfoldAll);
+        assert!(editor.hidden_lines(&editor.fold_regions()).is_empty());
+    }
+}
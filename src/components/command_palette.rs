@@ -0,0 +1,121 @@
+use super::keymap::{Keymap, KeymapProfile};
+use super::registry::{fuzzy_score, palette_entries, PaletteAction, PaletteEntry};
+use dioxus::prelude::*;
+
+#[derive(Props, PartialEq, Clone)]
+pub struct CommandPaletteProps {
+    /// 用于显示每个命令当前的按键
+    bindings: Signal<Keymap>,
+    /// 当前的按键方案，被它占用的按键不显示
+    profile: KeymapProfile,
+    on_run: EventHandler<PaletteAction>,
+    on_close: EventHandler<()>,
+}
+
+/// 命令面板(Ctrl+Shift+P)：模糊查找并执行任意命令
+#[component]
+pub fn CommandPalette(props: CommandPaletteProps) -> Element {
+    let bindings = props.bindings;
+    let profile = props.profile;
+    let mut query = use_signal(String::new);
+    let mut selected = use_signal(|| 0usize);
+    // 选中了需要输入路径的命令，等待输入
+    let mut prompt = use_signal(|| None::<PaletteAction>);
+
+    let matches = use_memo(move || {
+        let query = query();
+        let mut matches: Vec<(i32, PaletteEntry)> = palette_entries()
+            .into_iter()
+            .filter_map(|entry| Some((fuzzy_score(&query, &entry.search_text())?, entry)))
+            .collect();
+        // 分数相同时保持登记的顺序
+        matches.sort_by_key(|(score, _)| -score);
+        matches
+            .into_iter()
+            .map(|(_, entry)| entry)
+            .collect::<Vec<_>>()
+    });
+
+    let mut choose = move |entry: PaletteEntry| {
+        if entry.action.prompt().is_some() {
+            prompt.set(Some(entry.action));
+            query.set(String::new());
+        } else {
+            props.on_run.call(entry.action);
+        }
+    };
+
+    let on_keydown = move |e: Event<KeyboardData>| match e.key() {
+        Key::ArrowDown => {
+            e.prevent_default();
+            let len = matches.with(|m| m.len());
+            selected.set((selected() + 1).min(len.saturating_sub(1)));
+        }
+        Key::ArrowUp => {
+            e.prevent_default();
+            selected.set(selected().saturating_sub(1));
+        }
+        Key::Enter => {
+            e.prevent_default();
+            if let Some(action) = prompt() {
+                props.on_run.call(action.with_argument(&query()));
+            } else if let Some(entry) = matches.with(|m| m.get(selected()).cloned()) {
+                choose(entry);
+            }
+        }
+        Key::Escape => props.on_close.call(()),
+        _ => {}
+    };
+
+    let placeholder = match prompt() {
+        Some(action) => action.prompt().unwrap_or_default(),
+        None => "输入命令名称",
+    };
+
+    rsx! {
+        div {
+            style: "position: fixed; top: 48px; left: 50%; transform: translateX(-50%); z-index: 10; width: 480px; background: #f0f0f0; border: 1px solid #ddd; padding: 4px; font-size: 14px;",
+            input {
+                style: "width: 100%; box-sizing: border-box;",
+                placeholder: placeholder,
+                value: "{query}",
+                oninput: move |e| {
+                    query.set(e.value());
+                    selected.set(0);
+                },
+                onkeydown: on_keydown,
+                onmounted: move |e| {
+                    spawn(async move {
+                        let _ = e.set_focus(true).await;
+                    });
+                },
+            }
+            if prompt().is_none() {
+                div {
+                    style: "max-height: 320px; overflow-y: auto; margin-top: 4px;",
+                    for (index, entry) in matches().into_iter().enumerate() {
+                        div {
+                            key: "{entry.title}",
+                            style: if index == selected() {
+                                "display: flex; padding: 2px 4px; cursor: pointer; background: #cde;"
+                            } else {
+                                "display: flex; padding: 2px 4px; cursor: pointer;"
+                            },
+                            onclick: {
+                                let entry = entry.clone();
+                                move |_| choose(entry.clone())
+                            },
+                            span { style: "flex: 1;", "{entry.title}" }
+                            if let PaletteAction::Key(action) = &entry.action {
+                                span {
+                                    style: "color: gray;",
+                                    {bindings.with(|b| b.binding_for(action, profile)).unwrap_or_default()}
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,7 @@
+use std::path::PathBuf;
+
+/// 用户配置目录 `~/.config/editor-x`
+pub fn config_dir() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(".config").join("editor-x"))
+}
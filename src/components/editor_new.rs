@@ -1,23 +1,46 @@
+use super::clipboard::{read_clipboard, write_clipboard};
+use super::command::EditorCommand;
+use super::command_palette::CommandPalette;
+use super::emacs::Emacs;
+use super::find_bar::FindBar;
+use super::goto_line::GoToLine;
+use super::grapheme::{
+    advance_column, line_graphemes, next_grapheme_boundary, prev_grapheme_boundary,
+};
+use super::history::{Edit, EditKind, History, Transaction};
+use super::indent::IndentConfig;
+use super::keymap::{with_page_lines, Chord, KeyAction, KeyPress, Keymap, KeymapProfile};
+use super::line_ending::LineEnding;
+use super::macro_panel::MacroPanel;
+use super::macros::Macro;
+use super::position::{Offset, Position};
+use super::registry::PaletteAction;
+use super::search::SearchQuery;
+use super::selection::Selection;
+use super::vim::{Vim, VimOutcome};
+use super::word::{next_word_boundary, prev_word_boundary, word_range_at};
 use crate::components::handle_mouse_click;
-use crate::praser::{parse, SyntaxBlocks, SyntaxType, TextNode};
-use dioxus::logger::tracing::span;
+use crate::language::Language;
+use crate::praser::{is_open_bracket, matching_bracket, parse, FoldRegion, SyntaxBlocks, TextNode};
 use dioxus::prelude::*;
 use ropey::Rope;
-use std::f32::consts::E;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use tokio::task;
 
 static DEBUG: bool = true;
-static DEBUG0: bool = false;
-static DEBUG1: bool = false;
 
 const LINE_HEIGHT: usize = 26; // 根据实际情况调整
 const CHAR_WIDTH: usize = 10; // 根据实际情况调整
+const GUTTER_WIDTH: usize = 16; // 折叠箭头所在的边栏
 
 #[derive(Props, PartialEq, Clone)]
 pub struct EditorAreaProps {
     language: Signal<String>,
-    cursor_position: Signal<(usize, usize)>,
+    cursor_position: Signal<Position>,
+    indent: Signal<IndentConfig>,
+    keymap: Signal<KeymapProfile>,
 }
 
 #[derive(Props, PartialEq, Clone)]
@@ -30,15 +53,42 @@ pub struct TextareaProps {
 #[derive(Debug)]
 pub struct Editor {
     text: Arc<Mutex<Rope>>,
-    cursor_position: (usize, usize), // (line, column)
+    selections: Vec<Selection>, // 按位置排序且互不重叠
+    primary: usize,             // 主光标在 selections 中的下标
+    history: History,
+    indent: IndentConfig,
+    language: Language,
+    search: Option<SearchQuery>,
+    search_matches: Result<Vec<Range<Position>>, regex::Error>, // 按位置排序
+    folds: Vec<usize>,                                          // 已折叠区域的 anchor
+    path: Option<PathBuf>,                                      // 当前文件，未保存过时为 None
+    line_ending: LineEnding,                                    // 保存时使用的换行符
+    mixed_line_endings: bool, // 打开的文件混用了多种换行符，保存时会统一
+    command_log: Option<Vec<EditorCommand>>, // 记录 execute 执行的命令
 }
 
 #[allow(unused)]
 impl Editor {
     pub fn new() -> Self {
+        Self::with_text("")
+    }
+
+    /// 以给定文本创建编辑器(不记录历史)
+    pub fn with_text(text: &str) -> Self {
         Editor {
-            text: Arc::new(Mutex::new(Rope::new())),
-            cursor_position: (0, 0),
+            text: Arc::new(Mutex::new(Rope::from_str(&LineEnding::normalize(text)))),
+            selections: vec![Selection::cursor(Position::default())],
+            primary: 0,
+            history: History::default(),
+            indent: IndentConfig::default(),
+            language: Language::default(),
+            search: None,
+            search_matches: Ok(Vec::new()),
+            folds: Vec::new(),
+            path: None,
+            line_ending: LineEnding::detect(text),
+            mixed_line_endings: LineEnding::is_mixed(text),
+            command_log: None,
         }
     }
 
@@ -48,165 +98,1637 @@ impl Editor {
         rope.to_string()
     }
 
-    /// 插入文本
+    /// 当前文本的快照(克隆 Rope 的开销很小)
+    pub fn rope(&self) -> Rope {
+        self.text.lock().unwrap().clone()
+    }
+
+    /// 当前文件路径
+    pub fn file_path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// 文件的换行符
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    /// 打开的文本是否混用了多种换行符。保存时会统一为 `line_ending`
+    pub fn has_mixed_line_endings(&self) -> bool {
+        self.mixed_line_endings
+    }
+
+    /// 设置保存时使用的换行符。文本中始终是 `\n`，所以这不是一次编辑，
+    /// 不进入撤销历史；之后的文件偏移量按新的换行符计算
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        if line_ending != self.line_ending || self.mixed_line_endings {
+            self.history.mark_modified();
+        }
+        self.line_ending = line_ending;
+        self.mixed_line_endings = false;
+    }
+
+    /// 文本是否有未保存的修改
+    pub fn is_modified(&self) -> bool {
+        !self.history.is_clean()
+    }
+
+    /// 开始记录 `execute` 执行的命令，用于把 Vim 和 Emacs 的操作录入宏
+    pub fn start_command_log(&mut self) {
+        self.command_log = Some(Vec::new());
+    }
+
+    /// 停止记录并取出记录的命令
+    pub fn take_command_log(&mut self) -> Vec<EditorCommand> {
+        self.command_log.take().unwrap_or_default()
+    }
+
+    pub(crate) fn log_command(&mut self, command: &EditorCommand) {
+        if let Some(log) = &mut self.command_log {
+            log.push(command.clone());
+        }
+    }
+
+    /// 新建空白文档
+    pub fn new_document(&mut self) {
+        self.reset_text("", None);
+    }
+
+    /// 打开文件，替换当前文本并清空历史
+    pub fn open(&mut self, path: PathBuf) -> io::Result<()> {
+        let text = std::fs::read_to_string(&path)?;
+        self.reset_text(&text, Some(path));
+        Ok(())
+    }
+
+    fn reset_text(&mut self, text: &str, path: Option<PathBuf>) {
+        *self.text.lock().unwrap() = Rope::from_str(&LineEnding::normalize(text));
+        self.line_ending = LineEnding::detect(text);
+        self.mixed_line_endings = LineEnding::is_mixed(text);
+        self.selections = vec![Selection::cursor(Position::default())];
+        self.primary = 0;
+        self.history = History::default();
+        self.folds.clear();
+        self.path = path;
+        self.refresh_search();
+    }
+
+    /// 保存到给定路径并设为当前文件，未给出路径时保存到当前文件
+    pub fn save(&mut self, path: Option<PathBuf>) -> io::Result<PathBuf> {
+        let Some(path) = path.or_else(|| self.path.clone()) else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "没有文件名"));
+        };
+        std::fs::write(&path, self.line_ending.apply(&self.get_text()).as_bytes())?;
+        self.mixed_line_endings = false;
+        self.history.mark_saved();
+        self.path = Some(path.clone());
+        Ok(path)
+    }
+
+    /// 获取选中的文本，多个选区之间以换行分隔
+    pub fn get_selected_text(&self) -> String {
+        let rope = self.text.lock().unwrap();
+        self.selections
+            .iter()
+            .map(|s| {
+                let range = selection_range(&rope, s);
+                rope.slice(range).to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// 在每个光标处插入文本(有选区时替换选中内容)，换行符统一为 `\n`
     pub fn insert_text(&mut self, text: &str) {
-        let cursor_pos = self.cursor_position_to_byte_offset();
-        {
-            let mut rope = self.text.lock().unwrap();
-            rope.insert(cursor_pos, text);
+        let text = LineEnding::normalize(text);
+        self.edit_selections(EditKind::Typing, |_, range| (range, text.to_string()));
+    }
+
+    /// 输入单个字符，处理成对字符的自动补全、跳过闭合字符以及用成对字符包裹选区
+    pub fn type_char(&mut self, ch: char) {
+        let pairs = self.language.auto_pairs();
+        let closer = pairs.iter().find(|(open, _)| *open == ch).map(|p| p.1);
+        let is_closer = pairs.iter().any(|(_, close)| *close == ch);
+        if closer.is_none() && !is_closer {
+            self.insert_text(&ch.to_string());
+            return;
+        }
+
+        self.edit_selections_with(EditKind::Typing, |rope, range| {
+            let next = rope.get_char(range.end);
+            let prev = range.start.checked_sub(1).map(|i| rope.char(i));
+            // 下一个字符正是要输入的闭合字符时跳过它
+            if range.is_empty() && is_closer && next == Some(ch) {
+                return (range, String::new(), 1, 1);
+            }
+            let Some(close) = closer else {
+                return (range, ch.to_string(), 0, 0);
+            };
+            if !range.is_empty() {
+                let selected = rope.slice(range.clone()).to_string();
+                let len = selected.chars().count() as isize;
+                return (range, format!("{}{}{}", ch, selected, close), -len - 1, -1);
+            }
+            // 只在单词之外补全，引号还要求前面不是单词字符
+            let next_ok = next.is_none_or(|c| c.is_whitespace() || pairs.iter().any(|p| p.1 == c));
+            let prev_ok = ch != close || prev.is_none_or(|c| !c.is_alphanumeric() && c != '_');
+            if next_ok && prev_ok {
+                (range, format!("{}{}", ch, close), -1, -1)
+            } else {
+                (range, ch.to_string(), 0, 0)
+            }
+        });
+    }
+
+    /// 复制：返回选中的文本，没有选中内容时返回光标所在的整行
+    pub fn copy_text(&self) -> String {
+        if self.selections.iter().any(|s| !s.is_empty()) {
+            return self.get_selected_text();
+        }
+        let rope = self.text.lock().unwrap();
+        let mut text = String::new();
+        for selection in &self.selections {
+            text.push_str(&rope.line(selection.head.line).to_string());
+            if !text.ends_with('\n') {
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    /// 剪切：删除并返回选中的文本，没有选中内容时剪切光标所在的整行
+    pub fn cut_text(&mut self) -> String {
+        let text = self.copy_text();
+        if self.selections.iter().any(|s| !s.is_empty()) {
+            self.edit_selections(EditKind::Other, |_, range| (range, String::new()));
+        } else {
+            self.edit_selections(EditKind::Other, |rope, range| {
+                let line = rope.char_to_line(range.start);
+                let start = rope.line_to_char(line);
+                let end = rope.line_to_char((line + 1).min(rope.len_lines()));
+                (start..end, String::new())
+            });
+        }
+        text
+    }
+
+    /// 粘贴：多光标且行数与光标数相同时逐行分配，否则在每个光标处插入整段文本
+    pub fn paste_text(&mut self, text: &str) {
+        let text = &*LineEnding::normalize(text);
+        let lines: Vec<&str> = text.trim_end_matches('\n').split('\n').collect();
+        if self.selections.len() > 1 && lines.len() == self.selections.len() {
+            let mut lines = lines.into_iter();
+            self.edit_selections(EditKind::Other, |_, range| {
+                (range, lines.next().unwrap_or_default().to_string())
+            });
+        } else {
+            self.edit_selections(EditKind::Other, |_, range| (range, text.to_string()));
         }
-        self.update_cursor_position_after_insert(text);
     }
 
     /// 删除文本
     pub fn delete_text(&mut self, length: usize) {
-        let mut rope = self.text.lock().unwrap();
-        let cursor_pos = self.cursor_position_to_byte_offset();
-        rope.remove(cursor_pos..cursor_pos + length);
+        self.edit_selections(EditKind::Other, |rope, range| {
+            let end = (range.start + length).min(rope.len_chars());
+            (range.start..end, String::new())
+        });
+    }
+
+    /// 对每个选区计算一处替换，作为一次操作应用
+    fn edit_selections(
+        &mut self,
+        kind: EditKind,
+        mut f: impl FnMut(&Rope, Range<usize>) -> (Range<usize>, String),
+    ) {
+        let changes = {
+            let rope = self.text.lock().unwrap();
+            self.selections
+                .iter()
+                .map(|s| f(&rope, selection_range(&rope, s)))
+                .collect()
+        };
+        self.apply_changes(kind, changes);
+    }
+
+    /// 对每个选区计算一处替换，f 额外返回新选区 anchor 和 head 相对插入结尾的偏移
+    fn edit_selections_with(
+        &mut self,
+        kind: EditKind,
+        mut f: impl FnMut(&Rope, Range<usize>) -> (Range<usize>, String, isize, isize),
+    ) {
+        let mut offsets = Vec::new();
+        let changes = {
+            let rope = self.text.lock().unwrap();
+            self.selections
+                .iter()
+                .map(|s| {
+                    let (range, text, anchor, head) = f(&rope, selection_range(&rope, s));
+                    offsets.push((anchor, head));
+                    (range, text)
+                })
+                .collect()
+        };
+        self.apply_changes_with(kind, changes, |rope, cursors| {
+            let at = |idx: usize, offset: isize| {
+                char_to_cursor(
+                    rope,
+                    (idx as isize + offset).clamp(0, rope.len_chars() as isize) as usize,
+                )
+            };
+            cursors
+                .into_iter()
+                .zip(offsets)
+                .map(|(idx, (anchor, head))| Selection::new(at(idx, anchor), at(idx, head)))
+                .collect()
+        });
     }
 
-    /// 更新光标位置
-    fn update_cursor_position_after_insert(&mut self, text: &str) {
-        let new_cursor_position = self.cursor_position.1 + text.chars().count();
-        self.cursor_position = (self.cursor_position.0, new_cursor_position);
+    /// 应用若干按原文本偏移量升序排列的替换，每处替换之后放置一个光标
+    fn apply_changes(&mut self, kind: EditKind, changes: Vec<(Range<usize>, String)>) {
+        self.apply_changes_with(kind, changes, |rope, cursors| {
+            cursors
+                .into_iter()
+                .map(|idx| Selection::cursor(char_to_cursor(rope, idx)))
+                .collect()
+        });
     }
 
-    /// 根据光标位置获取字节偏移量
-    fn cursor_position_to_byte_offset(&self) -> usize {
+    /// 应用若干按原文本偏移量升序排列的替换，作为一次操作记录到历史；
+    /// after 根据修改后的文本和每处替换结束的偏移量给出新的选区
+    fn apply_changes_with(
+        &mut self,
+        kind: EditKind,
+        changes: Vec<(Range<usize>, String)>,
+        after: impl FnOnce(&Rope, Vec<usize>) -> Vec<Selection>,
+    ) {
+        let cursor_before = self.selections.clone();
+        let mut edits = Vec::new();
+        let mut cursors = Vec::new();
+        {
+            let mut rope = self.text.lock().unwrap();
+            let mut delta: isize = 0;
+            let mut last_end = 0;
+            for (range, text) in changes {
+                // 与前一处替换重叠的部分已被删除
+                let range_start = range.start.max(last_end);
+                let range_end = range.end.max(range_start);
+                last_end = range_end;
+
+                let start = (range_start as isize + delta) as usize;
+                let end = (range_end as isize + delta) as usize;
+                let inserted_len = text.chars().count();
+                cursors.push(start + inserted_len);
+                delta += inserted_len as isize - (end - start) as isize;
+                if start == end && text.is_empty() {
+                    continue;
+                }
+
+                let removed = rope.slice(start..end).to_string();
+                rope.remove(start..end);
+                rope.insert(start, &text);
+                map_folds(&mut self.folds, start, end - start, inserted_len);
+                edits.push(Edit {
+                    offset: start,
+                    removed,
+                    inserted: text,
+                });
+            }
+            self.selections = after(&rope, cursors);
+        }
+        self.normalize_selections();
+        self.refresh_search();
+        self.history.push(Transaction {
+            kind,
+            edits,
+            cursor_before,
+            cursor_after: self.selections.clone(),
+        });
+    }
+
+    /// 开始一组修改，到对应的 end_undo_group 为止作为一次撤销
+    pub fn begin_undo_group(&mut self) {
+        self.history.begin_group();
+    }
+
+    pub fn end_undo_group(&mut self) {
+        self.history.end_group();
+    }
+
+    /// 撤销
+    pub fn undo(&mut self) {
+        let Some(transaction) = self.history.undo().cloned() else {
+            return;
+        };
+        {
+            let mut rope = self.text.lock().unwrap();
+            for edit in transaction.edits.iter().rev() {
+                let (removed, inserted) =
+                    (edit.inserted.chars().count(), edit.removed.chars().count());
+                rope.remove(edit.offset..edit.offset + removed);
+                rope.insert(edit.offset, &edit.removed);
+                map_folds(&mut self.folds, edit.offset, removed, inserted);
+            }
+        }
+        self.restore_selections(transaction.cursor_before);
+        self.refresh_search();
+    }
+
+    /// 重做
+    pub fn redo(&mut self) {
+        let Some(transaction) = self.history.redo().cloned() else {
+            return;
+        };
+        {
+            let mut rope = self.text.lock().unwrap();
+            for edit in transaction.edits.iter() {
+                let (removed, inserted) =
+                    (edit.removed.chars().count(), edit.inserted.chars().count());
+                rope.remove(edit.offset..edit.offset + removed);
+                rope.insert(edit.offset, &edit.inserted);
+                map_folds(&mut self.folds, edit.offset, removed, inserted);
+            }
+        }
+        self.restore_selections(transaction.cursor_after);
+        self.refresh_search();
+    }
+
+    fn restore_selections(&mut self, selections: Vec<Selection>) {
+        self.history.seal();
+        self.primary = selections.len().saturating_sub(1);
+        self.selections = selections;
+        self.reveal_selections();
+    }
+
+    /// 排序并合并重叠的选区，同时保持主光标
+    fn normalize_selections(&mut self) {
+        let primary = self.selections[self.primary.min(self.selections.len() - 1)];
+        self.selections.sort_by_key(|s| s.start());
+        let mut merged: Vec<Selection> = Vec::with_capacity(self.selections.len());
+        for selection in self.selections.drain(..) {
+            match merged.last_mut() {
+                Some(last)
+                    if selection.start() < last.end() || selection.start() == last.start() =>
+                {
+                    let start = last.start();
+                    let end = last.end().max(selection.end());
+                    *last = if last.head < last.anchor {
+                        Selection::new(end, start)
+                    } else {
+                        Selection::new(start, end)
+                    };
+                }
+                _ => merged.push(selection),
+            }
+        }
+        self.primary = merged
+            .iter()
+            .position(|s| *s == primary)
+            .or_else(|| {
+                merged
+                    .iter()
+                    .position(|s| s.start() <= primary.head && primary.head <= s.end())
+            })
+            .unwrap_or(merged.len() - 1);
+        self.selections = merged;
+        self.reveal_selections();
+    }
+
+    /// 根据字符偏移量获取光标位置
+    fn char_to_cursor(&self, char_idx: usize) -> Position {
         let rope = self.text.lock().unwrap();
-        let (line, col) = self.cursor_position;
-        rope.line_to_char(line) + col
+        char_to_cursor(&rope, char_idx)
     }
 
-    /// 设置光标位置
-    pub fn set_cursor_position(&mut self, line: usize, col: usize) {
-        self.cursor_position = (line, col);
+    /// 保存后的文件中每个换行符比文本中的 `\n` 多占的单位数，CRLF 为 1
+    fn line_break_extra(&self) -> usize {
+        self.line_ending.as_str().len() - 1
     }
 
-    /// 获取当前光标位置
-    pub fn get_cursor_position(&self) -> (usize, usize) {
-        self.cursor_position
+    /// 文件中任意单位的偏移量对应的位置，用于对接按字节或 UTF-16 计数的工具。
+    /// 偏移量按保存时的换行符计算，落在 `\r\n` 中间时取行尾
+    pub fn position_at(&self, offset: Offset) -> Position {
+        let rope = self.text.lock().unwrap();
+        let extra = self.line_break_extra();
+        // 第 line 行之前有 line 个换行符
+        let line_start =
+            |line: usize| offset.at(&rope, Position::new(line, 0)).value() + line * extra;
+        let (mut line, mut last) = (0, rope.len_lines() - 1);
+        while line < last {
+            let mid = (line + last).div_ceil(2);
+            if line_start(mid) <= offset.value() {
+                line = mid;
+            } else {
+                last = mid - 1;
+            }
+        }
+        let pos = Position::from_offset(&rope, offset.with_value(offset.value() - line * extra));
+        pos.min(Position::new(line, line_len(&rope, line)))
     }
 
-    /// 向右移动光标
-    pub fn move_cursor_right(&mut self) {
-        let mut cursor_position = self.cursor_position;
+    /// 主光标在文件中的偏移量，按保存时的换行符计算
+    fn cursor_offset(&self, unit: Offset) -> usize {
+        let pos = self.get_cursor_position();
+        let offset = unit.at(&self.text.lock().unwrap(), pos).value();
+        offset + pos.line * self.line_break_extra()
+    }
+
+    /// 主光标的显示列，制表符和宽字符按显示宽度计算
+    pub fn cursor_visual_column(&self) -> usize {
         let rope = self.text.lock().unwrap();
+        visual_column(&rope, self.get_cursor_position(), self.indent.width)
+    }
+
+    /// 主光标的字符偏移量
+    pub fn cursor_char_offset(&self) -> usize {
+        self.cursor_offset(Offset::Char(0))
+    }
+
+    /// 主光标的 UTF-8 字节偏移量
+    pub fn cursor_byte_offset(&self) -> usize {
+        self.cursor_offset(Offset::Utf8(0))
+    }
+
+    /// 主光标的 UTF-16 码元偏移量
+    pub fn cursor_utf16_offset(&self) -> usize {
+        self.cursor_offset(Offset::Utf16(0))
+    }
+
+    /// 对每个选区移动光标；select 为 true 时保留 anchor 以扩展选区
+    fn move_heads(&mut self, select: bool, f: impl Fn(&Rope, &Selection) -> Position) {
+        self.history.seal();
+        {
+            let rope = self.text.lock().unwrap();
+            for selection in self.selections.iter_mut() {
+                let head = f(&rope, selection);
+                *selection = if select {
+                    Selection::new(selection.anchor, head)
+                } else {
+                    Selection::cursor(head)
+                };
+            }
+        }
+        self.normalize_selections();
+    }
 
-        // 如果光标没有到达行尾，向右移动
-        if cursor_position.1 < rope.line(cursor_position.0).len_chars() {
-            cursor_position.1 += 1;
-        } else if cursor_position.0 < rope.lines().count() - 1 {
-            // 向下移动光标到下一行的开头
-            cursor_position.0 += 1;
-            cursor_position.1 = 0;
+    /// 对每个选区上下移动光标，f 根据当前行返回目标行；
+    /// 目标列取连续移动开始时记下的显示列，而不是上一行被截断后的列
+    fn move_heads_vertically(&mut self, select: bool, f: impl Fn(&Rope, usize) -> usize) {
+        self.history.seal();
+        {
+            let rope = self.text.lock().unwrap();
+            let tab_width = self.indent.width;
+            for selection in self.selections.iter_mut() {
+                let goal = selection
+                    .goal_column
+                    .unwrap_or_else(|| visual_column(&rope, selection.head, tab_width));
+                let line = f(&rope, selection.head.line);
+                let head = Position::new(line, column_at_visual(&rope, line, goal, tab_width));
+                let anchor = if select { selection.anchor } else { head };
+                *selection = Selection {
+                    anchor,
+                    head,
+                    goal_column: Some(goal),
+                };
+            }
         }
+        self.normalize_selections();
+    }
 
-        self.cursor_position = cursor_position;
+    /// 设置光标位置(只保留一个光标)，超出范围时取最近的有效位置
+    pub fn set_cursor_position(&mut self, pos: Position) {
+        self.history.seal();
+        let pos = self.clamp_position(pos);
+        self.selections = vec![Selection::cursor(pos)];
+        self.primary = 0;
+        self.reveal_selections();
     }
 
-    /// 向左移动光标
-    pub fn move_cursor_left(&mut self) {
-        let mut cursor_position = self.cursor_position;
-        if cursor_position.1 > 0 {
-            cursor_position.1 -= 1;
-        } else if cursor_position.0 > 0 {
-            // 向上移动光标到上一行的结尾
-            cursor_position.0 -= 1;
-            cursor_position.1 = self
-                .text
-                .lock()
-                .unwrap()
-                .line(cursor_position.0)
-                .len_chars();
+    /// 跳转到给定行列，超出范围时取最近的有效位置
+    pub fn go_to_position(&mut self, pos: Position) {
+        self.set_cursor_position(pos);
+    }
+
+    /// 超出范围的行列取最近的有效位置
+    fn clamp_position(&self, pos: Position) -> Position {
+        let rope = self.text.lock().unwrap();
+        let line = pos.line.min(rope.len_lines() - 1);
+        Position::new(line, pos.col.min(line_len(&rope, line)))
+    }
+
+    /// 跳转到给定偏移量
+    pub fn go_to_offset(&mut self, offset: Offset) {
+        let pos = self.position_at(offset);
+        self.set_cursor_position(pos);
+    }
+
+    /// 总行数
+    pub fn len_lines(&self) -> usize {
+        self.text.lock().unwrap().len_lines()
+    }
+
+    /// 获取主光标位置
+    pub fn get_cursor_position(&self) -> Position {
+        self.selections[self.primary].head
+    }
+
+    /// 获取主选区
+    pub fn get_selection(&self) -> Selection {
+        self.selections[self.primary]
+    }
+
+    /// 获取所有选区
+    pub fn get_selections(&self) -> &[Selection] {
+        &self.selections
+    }
+
+    /// 把主选区扩展到给定行列(只保留主选区)，超出范围时取最近的有效位置
+    pub fn select_to(&mut self, pos: Position) {
+        self.history.seal();
+        let pos = self.clamp_position(pos);
+        let anchor = self.get_selection().anchor;
+        self.selections = vec![Selection::new(anchor, pos)];
+        self.primary = 0;
+    }
+
+    /// 全选
+    pub fn select_all(&mut self) {
+        self.history.seal();
+        let end = {
+            let rope = self.text.lock().unwrap();
+            rope.len_chars()
+        };
+        self.selections = vec![Selection::new(
+            Position::default(),
+            self.char_to_cursor(end),
+        )];
+        self.primary = 0;
+    }
+
+    /// 在给定行列添加一个光标，并设为主光标，超出范围时取最近的有效位置
+    pub fn add_cursor(&mut self, pos: Position) {
+        let pos = self.clamp_position(pos);
+        self.add_selection(Selection::cursor(pos));
+    }
+
+    fn add_selection(&mut self, selection: Selection) {
+        self.history.seal();
+        self.selections.push(selection);
+        self.primary = self.selections.len() - 1;
+        self.normalize_selections();
+    }
+
+    /// 只保留主光标
+    pub fn clear_secondary_cursors(&mut self) {
+        self.history.seal();
+        let primary = self.selections[self.primary];
+        self.selections = vec![primary];
+        self.primary = 0;
+    }
+
+    /// 在主光标的上一行添加光标
+    pub fn add_cursor_above(&mut self) {
+        let Position { line, col } = self.get_cursor_position();
+        if line > 0 {
+            let col = col.min(line_len(&self.text.lock().unwrap(), line - 1));
+            self.add_cursor(Position::new(line - 1, col));
+        }
+    }
+
+    /// 在主光标的下一行添加光标
+    pub fn add_cursor_below(&mut self) {
+        let Position { line, col } = self.get_cursor_position();
+        let col = {
+            let rope = self.text.lock().unwrap();
+            if line + 1 >= rope.len_lines() {
+                return;
+            }
+            col.min(line_len(&rope, line + 1))
+        };
+        self.add_cursor(Position::new(line + 1, col));
+    }
+
+    /// 主选区为空时选中光标所在的单词，否则添加选中文本的下一处出现
+    pub fn add_next_occurrence(&mut self) {
+        let primary = self.get_selection();
+        let rope = self.text.lock().unwrap().clone();
+        if primary.is_empty() {
+            let idx = cursor_to_char(&rope, primary.head);
+            if let Some(range) = word_range_at(&rope, idx) {
+                self.selections[self.primary] = Selection::new(
+                    char_to_cursor(&rope, range.start),
+                    char_to_cursor(&rope, range.end),
+                );
+            }
+            return;
+        }
+
+        let needle = rope.slice(selection_range(&rope, &primary)).to_string();
+        let needle_len = needle.chars().count();
+        let haystack = rope.to_string();
+        let search_from = rope.char_to_byte(cursor_to_char(&rope, primary.end()));
+        let found = haystack[search_from..]
+            .find(&needle)
+            .map(|i| i + search_from)
+            .or_else(|| haystack.find(&needle));
+        if let Some(byte_idx) = found {
+            let start = rope.byte_to_char(byte_idx);
+            let selection = Selection::new(
+                char_to_cursor(&rope, start),
+                char_to_cursor(&rope, start + needle_len),
+            );
+            if !self.selections.contains(&selection) {
+                self.add_selection(selection);
+            }
         }
+    }
 
-        self.cursor_position = cursor_position;
+    /// 向右移动光标
+    pub fn move_cursor_right(&mut self, select: bool) {
+        let hidden = self.folded_lines();
+        self.move_heads(select, |rope, selection| {
+            if !select && !selection.is_empty() {
+                return selection.end();
+            }
+            // 按字素簇移动，行尾时移到下一行的开头(跳过折叠的行)
+            let idx = cursor_to_char(rope, selection.head);
+            let pos = char_to_cursor(rope, next_grapheme_boundary(rope, idx));
+            match hidden.iter().find(|r| r.contains(&pos.line)) {
+                Some(r) if r.end < rope.len_lines() => Position::new(r.end, 0),
+                _ => pos,
+            }
+        });
+    }
+
+    /// 向左移动光标
+    pub fn move_cursor_left(&mut self, select: bool) {
+        let hidden = self.folded_lines();
+        self.move_heads(select, |rope, selection| {
+            if !select && !selection.is_empty() {
+                return selection.start();
+            }
+            // 按字素簇移动，行首时移到上一行的结尾(跳过折叠的行)
+            let idx = cursor_to_char(rope, selection.head);
+            let pos = char_to_cursor(rope, prev_grapheme_boundary(rope, idx));
+            match hidden.iter().find(|r| r.contains(&pos.line)) {
+                Some(r) => Position::new(r.start - 1, line_len(rope, r.start - 1)),
+                None => pos,
+            }
+        });
     }
 
     /// 向下移动光标
-    pub fn move_cursor_down(&mut self) {
-        let mut cursor_position = self.cursor_position;
+    pub fn move_cursor_down(&mut self, select: bool) {
+        let hidden = self.folded_lines();
+        self.move_heads_vertically(select, |rope, line| {
+            next_visible_line(&hidden, line, rope.len_lines() - 1)
+        });
+    }
+
+    /// 向上移动光标
+    pub fn move_cursor_up(&mut self, select: bool) {
+        let hidden = self.folded_lines();
+        self.move_heads_vertically(select, |_, line| prev_visible_line(&hidden, line));
+    }
+
+    /// 移动到行首：先到第一个非空白字符，已在该处时到第 0 列
+    pub fn move_cursor_home(&mut self, select: bool) {
+        self.move_heads(select, |rope, selection| {
+            let Position { line, col } = selection.head;
+            let indent = rope
+                .line(line)
+                .chars()
+                .take_while(|c| *c == ' ' || *c == '\t')
+                .count()
+                .min(line_len(rope, line));
+            if col == indent {
+                Position::new(line, 0)
+            } else {
+                Position::new(line, indent)
+            }
+        });
+    }
+
+    /// 移动到行的第 0 列
+    pub fn move_cursor_line_begin(&mut self, select: bool) {
+        self.move_heads(select, |_, selection| Position::new(selection.head.line, 0));
+    }
+
+    /// 移动到行尾
+    pub fn move_cursor_end(&mut self, select: bool) {
+        self.move_heads(select, |rope, selection| {
+            let line = selection.head.line;
+            Position::new(line, line_len(rope, line))
+        });
+    }
+
+    /// 移动到文档开头
+    pub fn move_cursor_document_start(&mut self, select: bool) {
+        self.move_heads(select, |_, _| Position::default());
+    }
+
+    /// 移动到文档末尾
+    pub fn move_cursor_document_end(&mut self, select: bool) {
+        self.move_heads(select, |rope, _| char_to_cursor(rope, rope.len_chars()));
+    }
+
+    /// 向上翻页，lines 为可见行数(折叠的行不计)
+    pub fn move_cursor_page_up(&mut self, lines: usize, select: bool) {
+        let hidden = self.folded_lines();
+        self.move_heads_vertically(select, |_, line| {
+            (0..lines).fold(line, |line, _| prev_visible_line(&hidden, line))
+        });
+    }
+
+    /// 向下翻页，lines 为可见行数(折叠的行不计)
+    pub fn move_cursor_page_down(&mut self, lines: usize, select: bool) {
+        let hidden = self.folded_lines();
+        self.move_heads_vertically(select, |rope, line| {
+            let last_line = rope.len_lines() - 1;
+            (0..lines).fold(line, |line, _| next_visible_line(&hidden, line, last_line))
+        });
+    }
+
+    /// 按单词向右移动光标
+    pub fn move_cursor_word_right(&mut self, select: bool) {
+        self.move_heads(select, |rope, selection| {
+            let idx = cursor_to_char(rope, selection.head);
+            char_to_cursor(rope, next_word_boundary(rope, idx))
+        });
+    }
+
+    /// 按单词向左移动光标
+    pub fn move_cursor_word_left(&mut self, select: bool) {
+        self.move_heads(select, |rope, selection| {
+            let idx = cursor_to_char(rope, selection.head);
+            char_to_cursor(rope, prev_word_boundary(rope, idx))
+        });
+    }
+
+    /// 删除光标前的单词(Ctrl+Backspace)，有选区时删除选中内容
+    pub fn delete_word_backward(&mut self) {
+        self.edit_selections(EditKind::Other, |rope, range| {
+            if !range.is_empty() {
+                return (range, String::new());
+            }
+            (
+                prev_word_boundary(rope, range.start)..range.start,
+                String::new(),
+            )
+        });
+    }
+
+    /// 删除光标后的单词(Ctrl+Delete)，有选区时删除选中内容
+    pub fn delete_word_forward(&mut self) {
+        self.edit_selections(EditKind::Other, |rope, range| {
+            if !range.is_empty() {
+                return (range, String::new());
+            }
+            (
+                range.start..next_word_boundary(rope, range.start),
+                String::new(),
+            )
+        });
+    }
+
+    /// 换行，并按上一行和括号自动缩进
+    pub fn move_cursor_enter(&mut self) {
+        let mut backs = Vec::new();
+        let unit = self.indent.unit();
+        let changes = {
+            let rope = self.text.lock().unwrap();
+            self.selections
+                .iter()
+                .map(|s| {
+                    let range = selection_range(&rope, s);
+                    let (text, back) = newline_with_indent(&rope, range.clone(), &unit);
+                    backs.push(back);
+                    (range, text)
+                })
+                .collect()
+        };
+        self.apply_changes_with(EditKind::Other, changes, |rope, cursors| {
+            cursors
+                .into_iter()
+                .zip(backs)
+                .map(|(idx, back)| Selection::cursor(char_to_cursor(rope, idx - back)))
+                .collect()
+        });
+    }
+
+    /// 删除光标后的 length 个字素簇(delete)，有选区时删除选中内容
+    pub fn move_cursor_delete(&mut self, length: usize) {
+        self.edit_selections(EditKind::Other, |rope, range| {
+            if !range.is_empty() {
+                return (range, String::new());
+            }
+            let end = (0..length).fold(range.start, |idx, _| next_grapheme_boundary(rope, idx));
+            (range.start..end, String::new())
+        });
+    }
+
+    /// 删除光标前的 length 个字素簇(backspace)，有选区时删除选中内容
+    pub fn move_cursor_backspace(&mut self, length: usize) {
+        let pairs = self.language.auto_pairs();
+        self.edit_selections(EditKind::Other, |rope, range| {
+            if !range.is_empty() {
+                return (range, String::new());
+            }
+            // 光标位于一对空的成对字符之间时一起删除
+            if length == 1 && range.start > 0 {
+                let pair = (rope.char(range.start - 1), rope.get_char(range.start));
+                if pairs
+                    .iter()
+                    .any(|&(open, close)| pair == (open, Some(close)))
+                {
+                    return (range.start - 1..range.start + 1, String::new());
+                }
+            }
+            let start = (0..length).fold(range.start, |idx, _| prev_grapheme_boundary(rope, idx));
+            (start..range.start, String::new())
+        });
+    }
+
+    /// 选区覆盖的行块(首行, 末行)，相邻或重叠的行块会合并
+    fn line_blocks(&self) -> Vec<(usize, usize)> {
+        let mut blocks: Vec<(usize, usize)> = Vec::new();
+        for selection in &self.selections {
+            let (start, end) = (selection.start(), selection.end());
+            // 选区结束于某行行首时不包含该行
+            let last = if end.line > start.line && end.col == 0 {
+                end.line - 1
+            } else {
+                end.line
+            };
+            match blocks.last_mut() {
+                Some(block) if start.line <= block.1 + 1 => block.1 = block.1.max(last),
+                _ => blocks.push((start.line, last)),
+            }
+        }
+        blocks
+    }
+
+    /// 复制所在行(Ctrl+Shift+D)，光标移到复制出的行
+    pub fn duplicate_lines(&mut self) {
+        let blocks = self.line_blocks();
+        let changes = {
+            let rope = self.text.lock().unwrap();
+            blocks
+                .iter()
+                .map(|&(first, last)| {
+                    let start = rope.line_to_char(first);
+                    let end = line_end(&rope, last);
+                    let text = format!("\n{}", rope.slice(start..end));
+                    (end..end, text)
+                })
+                .collect()
+        };
+        let selections = self.selections.clone();
+        self.apply_changes_with(EditKind::Other, changes, |_, _| {
+            let mut shift = 0;
+            let mut moved = Vec::new();
+            for &(first, last) in &blocks {
+                shift += last - first + 1;
+                for selection in selections
+                    .iter()
+                    .filter(|s| first <= s.start().line && s.start().line <= last)
+                {
+                    let anchor = Position::new(selection.anchor.line + shift, selection.anchor.col);
+                    let head = Position::new(selection.head.line + shift, selection.head.col);
+                    moved.push(Selection::new(anchor, head));
+                }
+            }
+            moved
+        });
+    }
+
+    /// 上移所在行(Alt+Up)
+    pub fn move_lines_up(&mut self) {
+        self.move_lines(true);
+    }
+
+    /// 下移所在行(Alt+Down)
+    pub fn move_lines_down(&mut self) {
+        self.move_lines(false);
+    }
+
+    fn move_lines(&mut self, up: bool) {
+        let blocks = self.line_blocks();
+        let changes = {
+            let rope = self.text.lock().unwrap();
+            let last_line = rope.len_lines() - 1;
+            if (up && blocks[0].0 == 0) || (!up && blocks[blocks.len() - 1].1 >= last_line) {
+                return;
+            }
+            blocks
+                .iter()
+                .map(|&(first, last)| {
+                    // 与相邻的一行交换位置
+                    let (from, to) = if up {
+                        (first - 1, last)
+                    } else {
+                        (first, last + 1)
+                    };
+                    let mut lines: Vec<String> = (from..=to)
+                        .map(|l| {
+                            rope.slice(rope.line_to_char(l)..line_end(&rope, l))
+                                .to_string()
+                        })
+                        .collect();
+                    if up {
+                        lines.rotate_left(1);
+                    } else {
+                        lines.rotate_right(1);
+                    }
+                    (
+                        rope.line_to_char(from)..line_end(&rope, to),
+                        lines.join("\n"),
+                    )
+                })
+                .collect()
+        };
+        let selections = self.selections.clone();
+        self.apply_changes_with(EditKind::Other, changes, |_, _| {
+            let shift = |Position { line, col }| {
+                if up {
+                    Position::new(line - 1, col)
+                } else {
+                    Position::new(line + 1, col)
+                }
+            };
+            selections
+                .iter()
+                .map(|s| Selection::new(shift(s.anchor), shift(s.head)))
+                .collect()
+        });
+    }
+
+    /// 删除所在行(Ctrl+Shift+K)
+    pub fn delete_lines(&mut self) {
+        let blocks = self.line_blocks();
+        let changes = {
+            let rope = self.text.lock().unwrap();
+            blocks
+                .iter()
+                .map(|&(first, last)| {
+                    let range = if last + 1 < rope.len_lines() {
+                        rope.line_to_char(first)..rope.line_to_char(last + 1)
+                    } else {
+                        // 删除最后一行时连同前一行的换行符一起删除
+                        rope.line_to_char(first).saturating_sub(1)..rope.len_chars()
+                    };
+                    (range, String::new())
+                })
+                .collect()
+        };
+        let cols: Vec<usize> = blocks
+            .iter()
+            .map(|&(first, _)| {
+                self.selections
+                    .iter()
+                    .find(|s| s.start().line >= first)
+                    .map_or(0, |s| s.head.col)
+            })
+            .collect();
+        self.apply_changes_with(EditKind::Other, changes, |rope, cursors| {
+            cursors
+                .into_iter()
+                .zip(cols)
+                .map(|(idx, col)| {
+                    let line = rope.char_to_line(idx);
+                    Selection::cursor(Position::new(line, col.min(line_len(rope, line))))
+                })
+                .collect()
+        });
+    }
+
+    /// 合并行(Ctrl+J)：单行时与下一行合并，多行选区时合并所选的各行
+    pub fn join_lines(&mut self) {
+        let blocks = self.line_blocks();
+        let mut changes = Vec::new();
+        {
+            let rope = self.text.lock().unwrap();
+            for &(first, last) in &blocks {
+                let last = if first == last { last + 1 } else { last };
+                for line in first..last.min(rope.len_lines() - 1) {
+                    // 换行符以及下一行的缩进替换为一个空格
+                    let start = line_end(&rope, line);
+                    let next = rope.line_to_char(line + 1);
+                    let indent = rope
+                        .line(line + 1)
+                        .chars()
+                        .take_while(|c| *c == ' ' || *c == '\t')
+                        .count();
+                    let end = next + indent;
+                    let joined_empty =
+                        start == rope.line_to_char(line) || end == line_end(&rope, line + 1);
+                    let text = if joined_empty { "" } else { " " };
+                    changes.push((start..end, text.to_string()));
+                }
+            }
+        }
+        if changes.is_empty() {
+            return;
+        }
+        self.apply_changes_with(EditKind::Other, changes, |rope, cursors| {
+            // 每个行块保留一个光标，放在最后一处合并的位置
+            let mut selections: Vec<Selection> = Vec::new();
+            for idx in cursors {
+                let pos = char_to_cursor(rope, idx);
+                match selections.last_mut() {
+                    Some(last) if last.head.line == pos.line => *last = Selection::cursor(pos),
+                    _ => selections.push(Selection::cursor(pos)),
+                }
+            }
+            selections
+        });
+    }
+
+    /// 设置语言(决定自动补全的成对字符)
+    pub fn set_language(&mut self, language: Language) {
+        self.language = language;
+    }
+
+    /// 设置缩进方式
+    pub fn set_indent_config(&mut self, indent: IndentConfig) {
+        self.indent = indent;
+    }
+
+    pub fn get_indent_config(&self) -> IndentConfig {
+        self.indent
+    }
+
+    /// Tab：选区跨行时缩进所选的每一行，否则在光标处插入一级缩进
+    pub fn move_cursor_tab(&mut self) {
+        if self
+            .selections
+            .iter()
+            .all(|s| s.start().line == s.end().line)
+        {
+            let indent = self.indent;
+            self.edit_selections(EditKind::Other, |rope, range| {
+                let column = visual_column(rope, char_to_cursor(rope, range.start), indent.width);
+                (range, indent.unit_at(column))
+            });
+            return;
+        }
+
+        let unit = self.indent.unit();
+        self.change_lines(|line| {
+            // 空行不缩进
+            if line.trim().is_empty() {
+                (0, 0, String::new())
+            } else {
+                (0, 0, unit.clone())
+            }
+        });
+    }
+
+    /// Shift+Tab：所在的每一行减少一级缩进
+    pub fn dedent_lines(&mut self) {
+        let indent = self.indent;
+        self.change_lines(|line| (0, indent.dedent_len(line), String::new()));
+    }
+
+    /// Ctrl+/：切换所选各行的单行注释，语言没有单行注释时改为切换块注释
+    pub fn toggle_line_comment(&mut self) {
+        let Some(token) = self.language.comment_tokens().line else {
+            self.toggle_block_comment();
+            return;
+        };
+        let (all_blank, all_commented, min_indent) = {
+            let rope = self.text.lock().unwrap();
+            let lines: Vec<String> = self
+                .line_blocks()
+                .into_iter()
+                .flat_map(|(first, last)| first..=last)
+                .map(|line| rope.line(line).to_string())
+                .filter(|line| !line.trim().is_empty())
+                .collect();
+            (
+                lines.is_empty(),
+                !lines.is_empty()
+                    && lines
+                        .iter()
+                        .all(|line| line.trim_start().starts_with(token)),
+                lines
+                    .iter()
+                    .map(|line| leading_whitespace(line))
+                    .min()
+                    .unwrap_or(0),
+            )
+        };
+        let prefix = format!("{} ", token);
+        self.change_lines(|line| {
+            // 空行保持不变，除非所选的全是空行
+            if line.trim().is_empty() {
+                let inserted = if all_blank {
+                    prefix.clone()
+                } else {
+                    String::new()
+                };
+                return (0, 0, inserted);
+            }
+            if all_commented {
+                let column = leading_whitespace(line);
+                let rest = &line[line.len() - line.trim_start().len() + token.len()..];
+                let space = usize::from(rest.starts_with(' '));
+                (column, token.chars().count() + space, String::new())
+            } else {
+                (min_indent, 0, prefix.clone())
+            }
+        });
+    }
+
+    /// Ctrl+Shift+/：用块注释包裹选区(无选区时为所在行的内容)，已被包裹时去掉注释
+    pub fn toggle_block_comment(&mut self) {
+        let (open, close) = self.language.comment_tokens().block;
+        let (open_len, close_len) = (open.chars().count(), close.chars().count());
+        self.edit_selections_with(EditKind::Other, |rope, range| {
+            let cursor = range.start;
+            let target = if range.is_empty() {
+                let line = rope.char_to_line(cursor);
+                let text = rope.line(line).to_string();
+                let start = rope.line_to_char(line) + leading_whitespace(&text);
+                let end = rope.line_to_char(line) + text.trim_end().chars().count();
+                start..end.max(start)
+            } else {
+                range.clone()
+            };
+            let text = rope.slice(target.clone()).to_string();
+            let (replacement, prefix_delta) =
+                match text.strip_prefix(open).and_then(|t| t.strip_suffix(close)) {
+                    Some(inner) => {
+                        let front = open_len + usize::from(inner.starts_with(' '));
+                        let inner = inner.strip_prefix(' ').unwrap_or(inner);
+                        let inner = inner.strip_suffix(' ').unwrap_or(inner);
+                        (inner.to_string(), -(front as isize))
+                    }
+                    None => (
+                        format!("{} {} {}", open, text, close),
+                        open_len as isize + 1,
+                    ),
+                };
+            let new_len = replacement.chars().count() as isize;
+            if !range.is_empty() {
+                // 选中替换后的全部内容(去掉注释后)或被包裹的原文
+                let (anchor, head) = if prefix_delta < 0 {
+                    (-new_len, 0)
+                } else {
+                    (-new_len + prefix_delta, -(close_len as isize) - 1)
+                };
+                return (target, replacement, anchor, head);
+            }
+            // 光标保持在原来的文字上
+            let offset = (cursor as isize - target.start as isize + prefix_delta).clamp(0, new_len);
+            (target, replacement, offset - new_len, offset - new_len)
+        });
+    }
+
+    /// 对选区覆盖的每一行，f 返回 (列, 删除的字符数, 插入的文本)，并相应平移选区
+    fn change_lines(&mut self, mut f: impl FnMut(&str) -> (usize, usize, String)) {
+        let blocks = self.line_blocks();
+        let mut changes = Vec::new();
+        let mut deltas = std::collections::HashMap::new();
+        {
+            let rope = self.text.lock().unwrap();
+            for &(first, last) in &blocks {
+                for line in first..=last {
+                    let (column, removed, inserted) = f(&rope.line(line).to_string());
+                    if removed == 0 && inserted.is_empty() {
+                        continue;
+                    }
+                    let start = rope.line_to_char(line) + column;
+                    deltas.insert(line, (column, removed, inserted.chars().count()));
+                    changes.push((start..start + removed, inserted));
+                }
+            }
+        }
+        let selections = self.selections.clone();
+        self.apply_changes_with(EditKind::Other, changes, |_, _| {
+            let shift = |pos: Position| match deltas.get(&pos.line) {
+                Some(&(column, removed, inserted)) if pos.col >= column => {
+                    Position::new(pos.line, pos.col.max(column + removed) - removed + inserted)
+                }
+                _ => pos,
+            };
+            selections
+                .iter()
+                .map(|s| Selection::new(shift(s.anchor), shift(s.head)))
+                .collect()
+        });
+    }
+
+    /// 设置查找条件并高亮全部匹配，None 表示关闭查找
+    pub fn set_search_query(&mut self, query: Option<SearchQuery>) {
+        self.search = query;
+        self.refresh_search();
+    }
+
+    /// 文本或查找条件变化后重新查找
+    fn refresh_search(&mut self) {
+        let Some(query) = &self.search else {
+            self.search_matches = Ok(Vec::new());
+            return;
+        };
         let rope = self.text.lock().unwrap();
+        self.search_matches = query.find_all(&rope).map(|matches| {
+            matches
+                .into_iter()
+                .map(|m| char_to_cursor(&rope, m.start)..char_to_cursor(&rope, m.end))
+                .collect()
+        });
+    }
+
+    /// 当前的全部匹配，正则表达式无效时返回错误
+    pub fn search_matches(&self) -> Result<&[Range<Position>], &regex::Error> {
+        self.search_matches.as_deref()
+    }
+
+    /// 主选区恰好是第几个匹配
+    pub fn current_match_index(&self) -> Option<usize> {
+        let selection = self.get_selection();
+        let matches = self.search_matches().ok()?;
+        matches
+            .iter()
+            .position(|m| m.start == selection.start() && m.end == selection.end())
+    }
 
-        if cursor_position.0 < rope.lines().count() - 1 {
-            cursor_position.0 += 1;
-            cursor_position.1 =
-                std::cmp::min(cursor_position.1, rope.line(cursor_position.0).len_chars());
+    /// 选中下一个匹配(backwards 时为上一个)，到达末尾后从头开始，返回是否找到
+    pub fn find_next(&mut self, backwards: bool) -> bool {
+        let selection = self.get_selection();
+        let Ok(matches) = self.search_matches() else {
+            return false;
+        };
+        let found = if backwards {
+            matches
+                .iter()
+                .rev()
+                .find(|m| m.end <= selection.start())
+                .or(matches.last())
+        } else {
+            matches
+                .iter()
+                .find(|m| m.start >= selection.end())
+                .or(matches.first())
+        };
+        let Some(found) = found.cloned() else {
+            return false;
+        };
+        self.history.seal();
+        self.selections = vec![Selection::new(found.start, found.end)];
+        self.primary = 0;
+        self.reveal_selections();
+        true
+    }
+
+    /// 主选区是一个匹配时替换它，然后选中下一个匹配
+    pub fn replace_next(&mut self, replace: &str) {
+        let Some(query) = self.search.clone() else {
+            return;
+        };
+        let selection = self.get_selection();
+        let change = {
+            let rope = self.text.lock().unwrap();
+            let range = selection_range(&rope, &selection);
+            query
+                .replacements(&rope, replace)
+                .ok()
+                .and_then(|all| all.into_iter().find(|(m, _)| *m == range))
+        };
+        if let Some(change) = change {
+            self.apply_changes(EditKind::Other, vec![change]);
         }
+        self.find_next(false);
+    }
 
-        self.cursor_position = cursor_position;
+    /// 替换全部匹配(作为一次操作)，返回替换的个数
+    pub fn replace_all(&mut self, replace: &str) -> usize {
+        let Some(query) = self.search.clone() else {
+            return 0;
+        };
+        let changes = {
+            let rope = self.text.lock().unwrap();
+            query.replacements(&rope, replace).unwrap_or_default()
+        };
+        let count = changes.len();
+        if count > 0 {
+            // 光标放在最后一处替换之后
+            self.apply_changes_with(EditKind::Other, changes, |rope, cursors| {
+                let last = cursors.last().copied().unwrap_or(0);
+                vec![Selection::cursor(char_to_cursor(rope, last))]
+            });
+        }
+        count
     }
 
-    /// 向上移动光标
-    pub fn move_cursor_up(&mut self) {
-        let mut cursor_position = self.cursor_position;
-        if cursor_position.0 > 0 {
-            cursor_position.0 -= 1;
-            cursor_position.1 = std::cmp::min(
-                cursor_position.1,
-                self.text
-                    .lock()
-                    .unwrap()
-                    .line(cursor_position.0)
-                    .len_chars(),
-            );
+    /// 检查给定行列的字符是否在某个匹配中
+    pub fn is_search_match(&self, pos: Position) -> bool {
+        let Ok(matches) = &self.search_matches else {
+            return false;
+        };
+        let i = matches.partition_point(|m| m.end <= pos);
+        matches.get(i).is_some_and(|m| m.contains(&pos))
+    }
+
+    /// 主光标旁的括号及与之配对的括号(字符偏移量)，光标右侧的括号优先；
+    /// 第三项表示括号是否在光标右侧
+    fn bracket_pair_at_cursor(&self, syntax_blocks: &SyntaxBlocks) -> Option<(usize, usize, bool)> {
+        let rope = self.text.lock().unwrap();
+        let cursor = cursor_to_char(&rope, self.get_selection().head);
+        let pairs = syntax_blocks.bracket_pairs(&rope);
+        if let Some(&partner) = pairs.get(&cursor) {
+            return Some((cursor, partner, true));
+        }
+        let before = cursor.checked_sub(1)?;
+        pairs.get(&before).map(|&partner| (before, partner, false))
+    }
+
+    /// 需要高亮的一对括号的行列
+    pub fn matching_brackets(&self, syntax_blocks: &SyntaxBlocks) -> Vec<Position> {
+        let Some((bracket, partner, _)) = self.bracket_pair_at_cursor(syntax_blocks) else {
+            return Vec::new();
+        };
+        let rope = self.text.lock().unwrap();
+        vec![
+            char_to_cursor(&rope, bracket),
+            char_to_cursor(&rope, partner),
+        ]
+    }
+
+    /// Ctrl+Shift+\：跳到与光标旁括号配对的括号，再次跳转会回到原处
+    pub fn jump_to_matching_bracket(&mut self) {
+        let syntax_blocks = self.parse_text();
+        let Some((_, partner, right)) = self.bracket_pair_at_cursor(&syntax_blocks) else {
+            return;
+        };
+        let target = if right { partner } else { partner + 1 };
+        let pos = self.char_to_cursor(target);
+        self.set_cursor_position(pos);
+    }
+
+    /// 当前可折叠的区域
+    pub fn fold_regions(&self) -> Vec<FoldRegion> {
+        let syntax_blocks = self.parse_text();
+        let rope = self.text.lock().unwrap();
+        syntax_blocks.fold_regions(&rope)
+    }
+
+    pub fn is_folded(&self, region: &FoldRegion) -> bool {
+        self.folds.contains(&region.anchor)
+    }
+
+    /// 被折叠隐藏的行区间，按行号排序且互不重叠
+    pub fn hidden_lines(&self, regions: &[FoldRegion]) -> Vec<Range<usize>> {
+        let mut hidden: Vec<Range<usize>> = Vec::new();
+        for region in regions.iter().filter(|r| self.is_folded(r)) {
+            let range = region.header + 1..region.last + 1;
+            match hidden.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => hidden.push(range),
+            }
+        }
+        hidden
+    }
+
+    /// 当前被折叠隐藏的行区间，没有折叠时不解析文本
+    pub fn folded_lines(&self) -> Vec<Range<usize>> {
+        if self.folds.is_empty() {
+            return Vec::new();
+        }
+        self.hidden_lines(&self.fold_regions())
+    }
+
+    /// 折叠或展开以 line 为首行的区域(点击边栏的箭头)
+    pub fn toggle_fold(&mut self, line: usize) {
+        let Some(region) = self.fold_regions().into_iter().find(|r| r.header == line) else {
+            return;
+        };
+        if self.is_folded(&region) {
+            self.folds.retain(|anchor| *anchor != region.anchor);
+        } else {
+            self.folds.push(region.anchor);
+            self.reveal_header(region);
+        }
+    }
+
+    /// Ctrl+Shift+[：折叠包含主光标的最内层区域
+    pub fn fold_at_cursor(&mut self) {
+        let line = self.get_cursor_position().line;
+        let region = self
+            .fold_regions()
+            .into_iter()
+            .filter(|r| !self.is_folded(r) && r.header <= line && line <= r.last + 1)
+            .max_by_key(|r| r.header);
+        if let Some(region) = region {
+            self.folds.push(region.anchor);
+            self.reveal_header(region);
+        }
+    }
+
+    /// Ctrl+Shift+]：展开主光标所在行的折叠
+    pub fn unfold_at_cursor(&mut self) {
+        let line = self.get_cursor_position().line;
+        let regions = self.fold_regions();
+        self.folds.retain(|anchor| {
+            !regions
+                .iter()
+                .any(|r| r.anchor == *anchor && r.header <= line && line <= r.last + 1)
+        });
+    }
+
+    /// 折叠全部区域
+    pub fn fold_all(&mut self) {
+        for region in self.fold_regions() {
+            if !self.is_folded(&region) {
+                self.folds.push(region.anchor);
+                self.reveal_header(region);
+            }
+        }
+    }
+
+    /// 展开全部折叠
+    pub fn unfold_all(&mut self) {
+        self.folds.clear();
+    }
+
+    /// 折叠后把落在被隐藏行中的光标移到首行末尾
+    fn reveal_header(&mut self, region: FoldRegion) {
+        let hidden = region.header + 1..region.last + 1;
+        let header_end = {
+            let rope = self.text.lock().unwrap();
+            Position::new(region.header, line_len(&rope, region.header))
+        };
+        for selection in self.selections.iter_mut() {
+            if hidden.contains(&selection.head.line) {
+                *selection = Selection::cursor(header_end);
+            }
+        }
+        self.normalize_selections();
+    }
+
+    /// 展开隐藏了某个光标的折叠
+    fn reveal_selections(&mut self) {
+        if self.folds.is_empty() {
+            return;
         }
+        let regions = self.fold_regions();
+        let heads: Vec<usize> = self.selections.iter().map(|s| s.head.line).collect();
+        self.folds.retain(|anchor| {
+            !regions.iter().any(|r| {
+                r.anchor == *anchor && heads.iter().any(|line| r.header < *line && *line <= r.last)
+            })
+        });
+    }
+
+    /// 解析文本并返回语法块
+    pub fn parse_text(&self) -> SyntaxBlocks {
+        let rope = self.text.lock().unwrap();
+        let mut syntax_blocks = SyntaxBlocks::default();
+        parse(&rope, &mut syntax_blocks);
+        syntax_blocks
+    }
+
+    /// 检查是否有光标在给定行列
+    pub fn is_cursor_at(&self, pos: Position) -> bool {
+        self.selections.iter().any(|s| s.head == pos)
+    }
+
+    /// 检查给定行列的字符是否被选中
+    pub fn is_selected(&self, pos: Position) -> bool {
+        self.selections.iter().any(|s| s.contains(pos))
+    }
+}
+
+/// 根据字符偏移量获取行列
+pub(crate) fn char_to_cursor(rope: &Rope, char_idx: usize) -> Position {
+    Position::from_offset(rope, Offset::Char(char_idx))
+}
+
+/// 文本在 offset 处删除 removed 个字符并插入 inserted 个字符后，平移折叠的 anchor；
+/// anchor 被删除的折叠随之取消
+fn map_folds(folds: &mut Vec<usize>, offset: usize, removed: usize, inserted: usize) {
+    folds.retain_mut(|anchor| {
+        if *anchor >= offset + removed {
+            *anchor = *anchor - removed + inserted;
+            true
+        } else {
+            *anchor < offset
+        }
+    });
+}
+
+/// 行首空白的字符数
+fn leading_whitespace(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace() && *c != '\n')
+        .count()
+}
+
+/// 根据行列获取字符偏移量
+fn cursor_to_char(rope: &Rope, pos: Position) -> usize {
+    pos.char_offset(rope)
+}
+
+/// 选区对应的字符区间
+fn selection_range(rope: &Rope, selection: &Selection) -> Range<usize> {
+    cursor_to_char(rope, selection.start())..cursor_to_char(rope, selection.end())
+}
 
-        self.cursor_position = cursor_position;
-    }
+/// 光标在行内的显示列
+fn visual_column(rope: &Rope, pos: Position, tab_width: usize) -> usize {
+    let text = rope.line(pos.line).to_string();
+    line_graphemes(&text)
+        .take_while(|(start, _)| *start < pos.col)
+        .fold(0, |column, (_, grapheme)| {
+            advance_column(column, grapheme, tab_width)
+        })
+}
 
-    /// 换行
-    pub fn move_cursor_enter(&mut self) {
-        let cursor_pos = self.cursor_position_to_byte_offset();
-        {
-            let mut rope = self.text.lock().unwrap();
-            rope.insert(cursor_pos, "\n");
+/// 显示列对应的字符列，不会落在字素簇中间，也不会超过行尾
+fn column_at_visual(rope: &Rope, line: usize, visual: usize, tab_width: usize) -> usize {
+    let text = rope.line(line).to_string();
+    let len = line_len(rope, line);
+    let mut width = 0;
+    for (col, grapheme) in line_graphemes(&text) {
+        width = advance_column(width, grapheme, tab_width);
+        if width > visual || col >= len {
+            return col.min(len);
         }
-        self.cursor_position = (self.cursor_position.0 + 1, 0);
     }
+    len
+}
 
-    /// 删除字符(delete)
-    pub fn move_cursor_delete(&mut self, length: usize) {
-        let mut rope = self.text.lock().unwrap();
-        let cursor_pos = self.cursor_position_to_byte_offset();
-        if cursor_pos + length <= rope.len_chars() {
-            rope.remove(cursor_pos..cursor_pos + length);
-        }
-    }
+/// 替换 range 的换行文本：沿用当前行的缩进，开括号后多缩进一级；
+/// 光标位于一对括号之间时把闭括号拆到第三行。返回 (文本, 光标距插入结尾的字符数)
+fn newline_with_indent(rope: &Rope, range: Range<usize>, unit: &str) -> (String, usize) {
+    let line_start = rope.line_to_char(rope.char_to_line(range.start));
+    let before = rope.slice(line_start..range.start).to_string();
+    let indent: String = before
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .collect();
+    let opener = before
+        .chars()
+        .rev()
+        .find(|c| !c.is_whitespace())
+        .filter(|c| is_open_bracket(*c));
+    let Some(opener) = opener else {
+        return (format!("\n{}", indent), 0);
+    };
 
-    /// 删除字符(backspace)
-    pub fn move_cursor_backspace(&mut self, length: usize) {
-        let mut rope = self.text.lock().unwrap();
-        let cursor_pos = self.cursor_position_to_byte_offset();
-        if cursor_pos >= length {
-            rope.remove(cursor_pos - length..cursor_pos);
-        }
-        self.cursor_position = (
-            self.cursor_position.0,
-            self.cursor_position.1.saturating_sub(length),
-        );
+    let inner = format!("{}{}", indent, unit);
+    let next = rope.get_char(range.end);
+    if next.is_some() && next == matching_bracket(opener) {
+        (
+            format!("\n{}\n{}", inner, indent),
+            1 + indent.chars().count(),
+        )
+    } else {
+        (format!("\n{}", inner), 0)
     }
+}
 
-    pub fn move_cursor_tab(&mut self) {
-        self.insert_text("    ");
-        for _ in 0..4 {
-            self.insert_text(" ");
-        }
-        self.cursor_position = (self.cursor_position.0, self.cursor_position.1);
+/// line 的下一个未被折叠的行，不超过 last_line
+fn next_visible_line(hidden: &[Range<usize>], line: usize, last_line: usize) -> usize {
+    let line = match hidden.iter().find(|r| r.contains(&(line + 1))) {
+        Some(r) => r.end,
+        None => line + 1,
+    };
+    line.min(last_line)
+}
+
+/// line 的上一个未被折叠的行
+fn prev_visible_line(hidden: &[Range<usize>], line: usize) -> usize {
+    let line = line.saturating_sub(1);
+    match hidden.iter().find(|r| r.contains(&line)) {
+        Some(r) => r.start - 1,
+        None => line,
     }
+}
 
-    /// 解析文本并返回语法块
-    pub fn parse_text(&self) -> SyntaxBlocks {
-        let rope = self.text.lock().unwrap();
-        let mut syntax_blocks = SyntaxBlocks::default();
-        parse(&rope, &mut syntax_blocks);
-        syntax_blocks
+/// 行尾(换行符之前)的字符偏移量
+pub(crate) fn line_end(rope: &Rope, line: usize) -> usize {
+    rope.line_to_char(line) + line_len(rope, line)
+}
+
+/// 最后一行的行号(文本以换行结尾时，其后的空行不算)
+pub(crate) fn last_line(rope: &Rope) -> usize {
+    let lines = rope.len_lines();
+    if lines > 1 && rope.line(lines - 1).len_chars() == 0 {
+        lines - 2
+    } else {
+        lines - 1
     }
+}
 
-    /// 检查光标是否在给定行列
-    pub fn is_cursor_at(&self, line: usize, col: usize) -> bool {
-        self.cursor_position == (line, col)
+/// 行内可放置光标的字符数(不含换行符)
+pub(crate) fn line_len(rope: &Rope, line: usize) -> usize {
+    let slice = rope.line(line);
+    let len = slice.len_chars();
+    if len > 0 && slice.char(len - 1) == '\n' {
+        len - 1
+    } else {
+        len
     }
 }
 
@@ -216,21 +1738,24 @@ pub struct TextAreaProps {
     pub on_keydown: Callback<Event<KeyboardData>>,
     pub editor: Signal<Editor>,
     pub textarea_focus: Signal<Option<std::rc::Rc<MountedData>>>,
+    pub scroll_top: Signal<f64>,
+    /// 光标所在行在折叠后的显示行号
+    pub row: usize,
 }
 
 #[component]
 pub fn Textarea(props: TextAreaProps) -> Element {
-    let mut textarea_focus = props.textarea_focus.clone();
+    let mut textarea_focus = props.textarea_focus;
     rsx! {
         textarea {
             style: format!(
                 "position: absolute; top: {}px; left: {}px; width: 1px; height: {}px; opacity: 0;",
-                props.editor.with(|e| e.cursor_position.0 * LINE_HEIGHT + 65),
-                props.editor.with(|e| (e.cursor_position.1 + 1) * CHAR_WIDTH),
+                (props.row * LINE_HEIGHT + 65) as f64 - (props.scroll_top)(),
+                props.editor.with(|e| (e.cursor_visual_column() + 1) * CHAR_WIDTH + GUTTER_WIDTH),
                 LINE_HEIGHT
             ),
-            oninput: props.on_input.clone(),
-            onkeydown: props.on_keydown.clone(),
+            oninput: props.on_input,
+            onkeydown: props.on_keydown,
             autofocus: true,
             onmounted: move |evt| {
                 textarea_focus.set(Some(evt.data));
@@ -242,81 +1767,245 @@ pub fn Textarea(props: TextAreaProps) -> Element {
 #[component]
 pub fn EditorArea(props: EditorAreaProps) -> Element {
     let mut editor = use_signal(|| {
-        let mut editor = Editor::new();
         // 插入调试文本
-        editor.insert_text("fn main() {\n    println!(\"Hello, world!\");\n}\n");
-        editor.set_cursor_position(0, 0);
-        editor
+        Editor::with_text("fn main() {\n    println!(\"Hello, world!\");\n}\n")
+    });
+    let cursor_position = props.cursor_position;
+    let mut indent = props.indent;
+    let mut language = props.language;
+    use_effect(move || {
+        let language = Language::from_name(&language());
+        editor.with_mut(|editorx| editorx.set_language(language));
+    });
+    use_effect(move || {
+        let config = indent();
+        editor.with_mut(|editorx| editorx.set_indent_config(config));
     });
-    let cursor_position = props.cursor_position.clone();
     let is_handled_by_keydown = Signal::new(false);
+    let mut textarea_value = use_signal(String::new);
+    let mut viewport_height = use_signal(|| 0.0);
     let textarea_focus: Signal<Option<std::rc::Rc<MountedData>>> = use_signal(|| None);
+    // 查找栏：None 为关闭，Some(true) 时同时显示替换
+    let mut find_bar = use_signal(|| None::<bool>);
+    let mut goto_open = use_signal(|| false);
+    // 正在录制的宏，None 表示未在录制
+    let mut recording = use_signal(|| None::<Vec<EditorCommand>>);
+    let mut recorded = use_signal(Macro::default);
+    let mut macro_panel = use_signal(|| false);
+    let mut keymap = props.keymap;
+    let mut vim = use_signal(Vim::default);
+    // 切换到其他按键方案时结束 Vim 未完成的修改
+    use_effect(move || {
+        if keymap() != KeymapProfile::Vim {
+            editor.with_mut(|editorx| vim.with_mut(|vim| vim.reset(editorx)));
+        }
+    });
+    let mut emacs = use_signal(Emacs::default);
+    let mut bindings = use_signal(Keymap::load);
+    let mut palette_open = use_signal(|| false);
+    // 用 `·` 显示空格
+    let mut show_whitespace = use_signal(|| false);
+    // 命令面板中操作失败时的提示
+    let mut message = use_signal(String::new);
+    // 编辑区的滚动位置，用于鼠标命中和输入框定位
+    let mut scroll_top = use_signal(|| 0.0);
+    // 未被折叠的行，点击时据此把显示行换算为实际行
+    let visible_lines = use_memo(move || {
+        editor.with(|e| {
+            let hidden = e.folded_lines();
+            (0..e.len_lines())
+                .filter(|line| !hidden.iter().any(|r| r.contains(line)))
+                .collect::<Vec<usize>>()
+        })
+    });
+
+    // 执行命令(录制时同时记下)，复制和剪切的文本写入剪贴板
+    let mut run_command = move |command: EditorCommand| {
+        recording.with_mut(|commands| {
+            if let Some(commands) = commands {
+                commands.push(command.clone());
+            }
+        });
+        if keymap() == KeymapProfile::Vim {
+            vim.with_mut(|vim| vim.record(&command));
+        }
+        if let Some(text) = editor.with_mut(|editorx| editorx.execute(command)) {
+            spawn(write_clipboard(text));
+        }
+    };
+
+    // 执行按键绑定或命令面板中的动作，查找、跳转、宏和粘贴需要界面参与
+    let mut run_action = move |action: KeyAction| match action {
+        KeyAction::Command(command) => {
+            let page_lines = ((viewport_height() / LINE_HEIGHT as f64) as usize).max(1);
+            run_command(with_page_lines(command, page_lines))
+        }
+        KeyAction::Find => find_bar.set(Some(false)),
+        KeyAction::Replace => find_bar.set(Some(true)),
+        KeyAction::GoToLine => goto_open.set(true),
+        KeyAction::PasteClipboard => {
+            spawn(async move {
+                if let Some(text) = read_clipboard().await {
+                    run_command(EditorCommand::Paste(text));
+                }
+            });
+        }
+        KeyAction::ToggleMacroRecording => match recording.take() {
+            Some(commands) => recorded.set(Macro { commands }),
+            None => recording.set(Some(Vec::new())),
+        },
+        KeyAction::MacroPanel => macro_panel.set(true),
+        KeyAction::ReloadKeymap => bindings.set(Keymap::load()),
+        KeyAction::CommandPalette => palette_open.set(true),
+        KeyAction::ToggleWhitespace => show_whitespace.set(!show_whitespace()),
+    };
 
-    let on_click = move |e| {
+    let on_palette_run = move |action: PaletteAction| {
+        palette_open.set(false);
+        let result = match action {
+            PaletteAction::Key(action) => {
+                run_action(action);
+                Ok(())
+            }
+            PaletteAction::NewFile => {
+                editor.with_mut(|e| e.new_document());
+                Ok(())
+            }
+            PaletteAction::OpenFile(path) => editor.with_mut(|e| e.open(path)),
+            PaletteAction::SaveFile => editor.with_mut(|e| e.save(None)).map(|_| ()),
+            PaletteAction::SaveFileAs(path) => editor.with_mut(|e| e.save(Some(path))).map(|_| ()),
+            PaletteAction::SetLanguage(value) => {
+                language.set(value.name().to_string());
+                Ok(())
+            }
+            PaletteAction::SetKeymapProfile(profile) => {
+                keymap.set(profile);
+                Ok(())
+            }
+            PaletteAction::SetIndent(config) => {
+                indent.set(config);
+                Ok(())
+            }
+        };
+        match result {
+            Ok(()) => message.set(String::new()),
+            Err(err) => message.set(format!("操作失败：{}", err)),
+        }
+        // 命令打开了查找栏等输入框时由它获得焦点
+        if find_bar().is_none() && !goto_open() && !macro_panel() {
+            if let Some(evt) = textarea_focus() {
+                spawn(async move {
+                    let _ = evt.set_focus(true).await;
+                });
+            }
+        }
+    };
+
+    // 鼠标定位使用绝对位置，不录入宏
+    let on_click = move |e: MouseEvent| {
         if DEBUG {
             println!("Click: {:?}", e);
         }
+        let modifiers = e.modifiers();
         editor.with(|editorx| {
             handle_mouse_click(
                 e,
-                cursor_position.clone(),
+                cursor_position,
                 LINE_HEIGHT,
                 CHAR_WIDTH,
+                GUTTER_WIDTH,
+                editorx.indent.width,
+                scroll_top() as usize,
+                &visible_lines(),
                 &editorx.text.lock().unwrap(),
             );
         });
-        let (line, col) = cursor_position();
-        editor.with_mut(|editorx| editorx.set_cursor_position(line, col));
+        let pos = cursor_position();
+        editor.with_mut(|editorx| {
+            if modifiers.alt() {
+                editorx.execute(EditorCommand::AddCursor(pos));
+            } else if modifiers.shift() {
+                editorx.execute(EditorCommand::SelectTo(pos));
+            } else {
+                editorx.execute(EditorCommand::SetCursor(pos));
+            }
+        });
     };
 
     let on_keydown = {
-        let mut is_handled_by_keydown = is_handled_by_keydown.clone();
+        let mut is_handled_by_keydown = is_handled_by_keydown;
         move |e: Event<KeyboardData>| {
             if DEBUG {
                 println!("Keydown: {:?}", e.key());
             }
-            editor.with_mut(|editorx| match e.key() {
-                Key::ArrowLeft => {
-                    editorx.move_cursor_left();
-                    e.prevent_default();
-                }
-                Key::ArrowRight => {
-                    editorx.move_cursor_right();
-                    e.prevent_default();
-                }
-                Key::ArrowUp => {
-                    editorx.move_cursor_up();
-                    e.prevent_default();
-                }
-                Key::ArrowDown => {
-                    editorx.move_cursor_down();
-                    e.prevent_default();
-                }
-                Key::Enter => {
-                    editorx.move_cursor_enter();
-                    e.prevent_default();
-                }
-                Key::Backspace => {
-                    editorx.move_cursor_backspace(1);
-                    e.prevent_default();
+            let modifiers = e.modifiers();
+            // Vim 和 Emacs 方案先处理按键，未处理的再交给默认绑定
+            let key = e.key();
+            let handled = match keymap() {
+                KeymapProfile::Default => false,
+                KeymapProfile::Vim => {
+                    let outcome = editor.with_mut(|editorx| {
+                        editorx.start_command_log();
+                        vim.with_mut(|vim| vim.handle_key(editorx, &key, modifiers))
+                    });
+                    if outcome == VimOutcome::Quit {
+                        close_window();
+                    }
+                    outcome != VimOutcome::Unhandled
                 }
-                Key::Delete => {
-                    editorx.move_cursor_delete(1);
-                    e.prevent_default();
+                KeymapProfile::Emacs => editor.with_mut(|editorx| {
+                    editorx.start_command_log();
+                    emacs.with_mut(|emacs| emacs.handle_key(editorx, &key, modifiers))
+                }),
+            };
+            // Vim 和 Emacs 执行的命令同样录入宏
+            if keymap() != KeymapProfile::Default {
+                let executed = editor.with_mut(|editorx| editorx.take_command_log());
+                recording.with_mut(|commands| {
+                    if let Some(commands) = commands {
+                        commands.extend(executed);
+                    }
+                });
+            }
+            if handled {
+                e.prevent_default();
+                is_handled_by_keydown.set(true);
+                return;
+            }
+            let press = match Chord::from_key(&key, modifiers) {
+                Some(chord) => bindings.with_mut(|bindings| bindings.press(chord)),
+                None => KeyPress::Unbound,
+            };
+            let handled = match press {
+                KeyPress::Action(action) => {
+                    run_action(action);
+                    true
                 }
-                Key::Tab => editorx.move_cursor_tab(),
-                _ => {}
-            });
-            is_handled_by_keydown.set(true);
+                KeyPress::Pending | KeyPress::Cancelled => true,
+                KeyPress::Unbound => false,
+            };
+            // 已处理的按键不再由 on_input 插入
+            if handled {
+                e.prevent_default();
+            }
+            is_handled_by_keydown.set(handled);
+            if find_bar().is_some() || goto_open() || macro_panel() || palette_open() {
+                return;
+            }
             if let Some(evt) = textarea_focus() {
-                evt.set_focus(true);
+                spawn(async move {
+                    let _ = evt.set_focus(true).await;
+                });
             }
         }
     };
 
     let on_input = {
-        let mut is_handled_by_keydown = is_handled_by_keydown.clone();
+        let mut is_handled_by_keydown = is_handled_by_keydown;
         move |e: Event<FormData>| {
+            // textarea 的内容只会增长，新输入的部分就是与上次内容的差
+            let value = e.value();
+            let previous = textarea_value.replace(value.clone());
             if is_handled_by_keydown() {
                 is_handled_by_keydown.set(false);
                 return;
@@ -324,32 +2013,168 @@ pub fn EditorArea(props: EditorAreaProps) -> Element {
             if DEBUG {
                 println!("Input: {:?}", e.data());
             }
-            editor.with_mut(|editorx| {
-                if let Some(last_char) = e.value().chars().last() {
-                    if last_char != '\n' || last_char != '\t' {
-                        editorx.insert_text(&last_char.to_string());
-                    }
-                }
-            });
+            let inserted = match value.strip_prefix(previous.as_str()) {
+                Some(inserted) => inserted.to_string(),
+                None => value.chars().last().map(String::from).unwrap_or_default(),
+            };
+            if inserted.chars().count() > 1 {
+                run_command(EditorCommand::Paste(inserted));
+            } else if let Some(ch) = inserted.chars().next() {
+                run_command(EditorCommand::TypeChar(ch));
+            }
         }
     };
 
     let syntax_blocks = editor.with(|e| e.parse_text());
+    let brackets = editor.with(|e| e.matching_brackets(&syntax_blocks));
+    let fold_regions = editor.with(|e| syntax_blocks.fold_regions(&e.text.lock().unwrap()));
+    let hidden = editor.with(|e| e.hidden_lines(&fold_regions));
+    let cursor_line = editor.with(|e| e.get_cursor_position().line);
+    let tab_width = editor.with(|e| e.get_indent_config().width);
 
     rsx! {
+        if goto_open() {
+            GoToLine {
+                editor: editor,
+                on_close: move |_| {
+                    goto_open.set(false);
+                    let line = editor.with(|e| e.get_cursor_position().line);
+                    // 把光标所在行滚动到可见区域，并记录滚动后的位置
+                    let mut eval = document::eval(&format!(
+                        "const line = document.getElementById('editor-line-{}');
+                        if (line) {{ line.scrollIntoView({{ block: 'center' }}); }}
+                        dioxus.send(line ? line.parentElement.scrollTop : 0);",
+                        line
+                    ));
+                    spawn(async move {
+                        if let Ok(top) = eval.recv::<f64>().await {
+                            scroll_top.set(top);
+                        }
+                        if let Some(evt) = textarea_focus() {
+                            let _ = evt.set_focus(true).await;
+                        }
+                    });
+                },
+            }
+        }
+        if palette_open() {
+            CommandPalette {
+                bindings: bindings,
+                profile: keymap(),
+                on_run: on_palette_run,
+                on_close: move |_| {
+                    palette_open.set(false);
+                    if let Some(evt) = textarea_focus() {
+                        spawn(async move {
+                            let _ = evt.set_focus(true).await;
+                        });
+                    }
+                },
+            }
+        }
+        if macro_panel() {
+            MacroPanel {
+                editor: editor,
+                recorded: recorded,
+                on_close: move |_| {
+                    macro_panel.set(false);
+                    if let Some(evt) = textarea_focus() {
+                        spawn(async move {
+                            let _ = evt.set_focus(true).await;
+                        });
+                    }
+                },
+            }
+        }
+        match keymap() {
+            KeymapProfile::Default => rsx! {},
+            KeymapProfile::Vim => rsx! {
+                div {
+                    style: "position: fixed; bottom: 8px; left: 16px; z-index: 10; font-family: monospace; font-size: 14px;",
+                    "{vim.with(|v| v.status())}"
+                }
+            },
+            KeymapProfile::Emacs => rsx! {
+                div {
+                    style: "position: fixed; bottom: 8px; left: 16px; z-index: 10; font-family: monospace; font-size: 14px;",
+                    "{emacs.with(|e| e.status().to_string())}"
+                }
+            },
+        }
+        if !bindings.with(|b| b.status()).is_empty() || !message().is_empty() {
+            div {
+                style: "position: fixed; bottom: 8px; left: 50%; transform: translateX(-50%); z-index: 10; color: gray; font-size: 14px;",
+                "{bindings.with(|b| b.status())} {message}"
+            }
+        }
+        div {
+            style: "position: fixed; bottom: 8px; right: 16px; z-index: 10; display: flex; gap: 16px; font-size: 14px;",
+            if recording.with(|r| r.is_some()) {
+                span {
+                    style: "color: #c33;",
+                    "● 正在录制宏 (Ctrl+Shift+R 停止)"
+                }
+            }
+            span {
+                style: "color: gray;",
+                title: if editor.with(|e| e.has_mixed_line_endings()) {
+                    "文件混用了多种换行符，保存时统一为此换行符，可在命令面板中转换"
+                } else {
+                    "换行符，可在命令面板中转换"
+                },
+                "{editor.with(|e| e.line_ending().label())}"
+                if editor.with(|e| e.has_mixed_line_endings()) {
+                    " (混合)"
+                }
+            }
+        }
+        if let Some(replace) = find_bar() {
+            FindBar {
+                editor: editor,
+                replace: replace,
+                on_close: move |_| {
+                    find_bar.set(None);
+                    if let Some(evt) = textarea_focus() {
+                        spawn(async move {
+                            let _ = evt.set_focus(true).await;
+                        });
+                    }
+                },
+            }
+        }
         div {
             style: "flex: 1 1 auto; overflow: hidden; font-family: monospace; font-size: 16px;",
-            onclick: on_click.clone(),
+            onclick: on_click,
+            onresize: move |e: Event<ResizeData>| {
+                if let Ok(size) = e.get_content_box_size() {
+                    viewport_height.set(size.height);
+                }
+            },
             Textarea {
-                on_keydown: on_keydown.clone(),
-                on_input: on_input.clone(),
-                editor: editor.clone(),
-                textarea_focus: textarea_focus.clone(),
+                on_keydown: on_keydown,
+                on_input: on_input,
+                editor: editor,
+                textarea_focus: textarea_focus,
+                scroll_top: scroll_top,
+                row: visible_lines().iter().position(|l| *l == cursor_line).unwrap_or(cursor_line),
             }
-            for line_index in 0..syntax_blocks.len() {
+            for line_index in (0..syntax_blocks.len()).filter(|line| !hidden.iter().any(|r| r.contains(line))) {
                 div {
-                    style: "white-space: pre; font-family: monospace; font-size: 16px; padding: 4px;",
-                    if editor.with(|e| e.is_cursor_at(line_index, 0)) {
+                    id: "editor-line-{line_index}",
+                    style: format!("white-space: pre; font-family: monospace; font-size: 16px; padding: 4px; tab-size: {};", tab_width),
+                    span {
+                        style: format!("width: {}px; display: inline-block; color: gray; cursor: pointer;", GUTTER_WIDTH),
+                        onclick: move |e| {
+                            e.stop_propagation();
+                            editor.with_mut(|editorx| editorx.toggle_fold(line_index));
+                        },
+                        match fold_regions.iter().find(|r| r.header == line_index) {
+                            Some(region) if editor.with(|e| e.is_folded(region)) => "▸",
+                            Some(_) => "▾",
+                            None => " ",
+                        }
+                    }
+                    if editor.with(|e| e.is_cursor_at(Position::new(line_index, 0))) {
                         span {
                             style: format!("width: {}px; ma; position: relative; margin-left: -1px; display: inline-block; border-right: 2px solid black;", CHAR_WIDTH),
                             " "
@@ -367,12 +2192,8 @@ pub fn EditorArea(props: EditorAreaProps) -> Element {
                                 let text = rope.slice(range.clone()).to_string();
                                 let mut rendered_text = String::new();
                                 let char_count = syntax_blocks.char_count_up_to(line_index, col_index);
-                                for (i, ch) in text.chars().enumerate() {
-                                    if editor.with(|e| e.is_cursor_at(line_index, char_count + i + 1)) {
-                                        rendered_text.push_str(&format!("<span style=\"position: relative; margin-left:-1px; border-right: 2px solid black;\">{}</span>", ch));
-                                    } else {
-                                        rendered_text.push(ch);
-                                    }
+                                for (i, grapheme) in line_graphemes(&text) {
+                                    editor.with(|e| rendered_text.push_str(&render_grapheme(e, &brackets, show_whitespace(), Position::new(line_index, char_count + i), grapheme)));
                                 }
                                 rsx! {
                                     span {
@@ -382,15 +2203,16 @@ pub fn EditorArea(props: EditorAreaProps) -> Element {
                                 }
                             },
                             TextNode::LineOfChars { len, char } => {
-                                let text = char.to_string().repeat(*len);
                                 let char_count = syntax_blocks.char_count_up_to(line_index, col_index);
+                                // 行首空白：空格显示为占位符，制表符保留以按 tab-size 显示
+                                let text: String = editor.with(|e| {
+                                    let rope = e.text.lock().unwrap();
+                                    let start = rope.line_to_char(line_index) + char_count;
+                                    rope.slice(start..start + len).chars().map(|c| if c == '\t' { c } else { *char }).collect()
+                                });
                                 let mut rendered_text = String::new();
-                                for (i, ch) in text.chars().enumerate() {
-                                    if editor.with(|e| e.is_cursor_at(line_index, char_count + i + 1)) {
-                                        rendered_text.push_str(&format!("<span style=\"position: relative; margin-left:-1px; border-right: 2px solid black;\">{}</span>", ch));
-                                    } else {
-                                        rendered_text.push(ch);
-                                    }
+                                for (i, grapheme) in line_graphemes(&text) {
+                                    editor.with(|e| rendered_text.push_str(&render_grapheme(e, &brackets, show_whitespace(), Position::new(line_index, char_count + i), grapheme)));
                                 }
                                 rsx! {
                                     span {
@@ -400,8 +2222,671 @@ pub fn EditorArea(props: EditorAreaProps) -> Element {
                             }
                         }
                     }
+                    if hidden.iter().any(|r| r.start == line_index + 1) {
+                        span {
+                            style: "margin-left: 4px; padding: 0 4px; background: #eee; color: gray;",
+                            "⋯"
+                        }
+                    }
                 }
             }
         }
     }
 }
+
+/// 关闭窗口(Vim 的 `:q`)，网页版没有可关闭的窗口
+fn close_window() {
+    #[cfg(feature = "desktop")]
+    dioxus::desktop::window().close();
+}
+
+/// 渲染一个字素簇(起始位置为 pos)，附带选区背景和光标
+fn render_grapheme(
+    editor: &Editor,
+    brackets: &[Position],
+    show_whitespace: bool,
+    pos: Position,
+    grapheme: &str,
+) -> String {
+    let mut escaped = String::new();
+    let mut style = String::new();
+    if show_whitespace && grapheme == " " {
+        escaped.push('·');
+        style.push_str("color: #bbb;");
+    }
+    for ch in grapheme.chars() {
+        match ch {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            ' ' if show_whitespace => {}
+            _ => escaped.push(ch),
+        }
+    }
+    if editor.is_selected(pos) {
+        style.push_str("background: rgba(100, 150, 255, 0.4);");
+    } else if editor.is_search_match(pos) {
+        style.push_str("background: rgba(255, 200, 0, 0.4);");
+    }
+    if brackets.contains(&pos) {
+        style.push_str("outline: 1px solid gray;");
+    }
+    let end = Position::new(pos.line, pos.col + grapheme.chars().count());
+    if editor.is_cursor_at(end) {
+        style.push_str("position: relative; margin-left:-1px; border-right: 2px solid black;");
+    }
+    if style.is_empty() {
+        escaped
+    } else {
+        format!("<span style=\"{}\">{}</span>", style, escaped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(line: usize, col: usize) -> Position {
+        Position::new(line, col)
+    }
+
+    fn editor_at(text: &str, pos: Position) -> Editor {
+        let mut editor = Editor::with_text(text);
+        editor.set_cursor_position(pos);
+        editor
+    }
+
+    /// 选中 from 到 to 的编辑器
+    fn editor_selecting(text: &str, from: Position, to: Position) -> Editor {
+        let mut editor = editor_at(text, from);
+        editor.select_to(to);
+        editor
+    }
+
+    /// 在每个位置放一个光标的编辑器
+    fn editor_with_cursors(text: &str, cursors: &[Position]) -> Editor {
+        let mut editor = editor_at(text, cursors[0]);
+        for &pos in &cursors[1..] {
+            editor.add_cursor(pos);
+        }
+        editor
+    }
+
+    fn heads(editor: &Editor) -> Vec<Position> {
+        editor.get_selections().iter().map(|s| s.head).collect()
+    }
+
+    /// 执行 f 后文本为 expected，一次撤销恢复原文
+    fn check_undo(editor: &mut Editor, f: impl FnOnce(&mut Editor), expected: &str) {
+        let before = editor.get_text();
+        f(editor);
+        assert_eq!(editor.get_text(), expected);
+        editor.undo();
+        assert_eq!(editor.get_text(), before);
+        editor.redo();
+        assert_eq!(editor.get_text(), expected);
+    }
+
+    #[test]
+    fn goal_column_survives_short_lines() {
+        let mut editor = editor_at("hello world\nhi\n\nhello world", at(0, 8));
+        editor.move_cursor_down(false);
+        assert_eq!(editor.get_cursor_position(), at(1, 2));
+        editor.move_cursor_down(false);
+        assert_eq!(editor.get_cursor_position(), at(2, 0));
+        editor.move_cursor_down(false);
+        assert_eq!(editor.get_cursor_position(), at(3, 8));
+        for _ in 0..3 {
+            editor.move_cursor_up(false);
+        }
+        assert_eq!(editor.get_cursor_position(), at(0, 8));
+        // 水平移动后重新记录目标列
+        editor.move_cursor_left(false);
+        editor.move_cursor_down(false);
+        editor.move_cursor_down(false);
+        editor.move_cursor_down(false);
+        assert_eq!(editor.get_cursor_position(), at(3, 7));
+    }
+
+    #[test]
+    fn goal_column_is_visual_on_wide_lines() {
+        // 中文每个字占两列
+        let mut editor = editor_at("abcdef\n中文字\nabcdef", at(0, 4));
+        editor.move_cursor_down(false);
+        assert_eq!(editor.get_cursor_position(), at(1, 2));
+        editor.move_cursor_down(false);
+        assert_eq!(editor.get_cursor_position(), at(2, 4));
+
+        // 目标列落在宽字符中间时取它前面的位置，继续移动仍回到原来的列
+        let mut editor = editor_at("abcdef\n中文字\nabcdef", at(0, 3));
+        editor.move_cursor_down(false);
+        assert_eq!(editor.get_cursor_position(), at(1, 1));
+        editor.move_cursor_down(false);
+        assert_eq!(editor.get_cursor_position(), at(2, 3));
+
+        // 从宽字符行出发，按显示列而不是字符列对齐
+        let mut editor = editor_at("中文字\nabcdef", at(0, 2));
+        editor.move_cursor_down(false);
+        assert_eq!(editor.get_cursor_position(), at(1, 4));
+    }
+
+    #[test]
+    fn goal_column_on_narrow_non_ascii_lines() {
+        // é、ж 都只占一列，上下移动不会漂移
+        let mut editor = editor_at("ééééé\nжжжжж\nabcde", at(0, 3));
+        editor.move_cursor_down(false);
+        assert_eq!(editor.get_cursor_position(), at(1, 3));
+        editor.move_cursor_down(false);
+        assert_eq!(editor.get_cursor_position(), at(2, 3));
+        editor.move_cursor_up(false);
+        editor.move_cursor_up(false);
+        assert_eq!(editor.get_cursor_position(), at(0, 3));
+    }
+
+    #[test]
+    fn duplicate_lines() {
+        let mut editor = editor_selecting("a\nb\nc", at(0, 0), at(1, 1));
+        check_undo(&mut editor, Editor::duplicate_lines, "a\nb\na\nb\nc");
+        let selection = editor.get_selection();
+        assert_eq!((selection.anchor, selection.head), (at(2, 0), at(3, 1)));
+
+        let mut editor = editor_with_cursors("a\nb\nc", &[at(0, 1), at(2, 0)]);
+        check_undo(&mut editor, Editor::duplicate_lines, "a\na\nb\nc\nc");
+        assert_eq!(heads(&editor), [at(1, 1), at(4, 0)]);
+    }
+
+    #[test]
+    fn move_lines() {
+        let mut editor = editor_selecting("a\nb\nc\nd", at(1, 0), at(2, 1));
+        check_undo(&mut editor, Editor::move_lines_up, "b\nc\na\nd");
+        let selection = editor.get_selection();
+        assert_eq!((selection.anchor, selection.head), (at(0, 0), at(1, 1)));
+        check_undo(&mut editor, Editor::move_lines_down, "a\nb\nc\nd");
+
+        let mut editor = editor_with_cursors("a\nb\nc\nd", &[at(0, 0), at(2, 1)]);
+        check_undo(&mut editor, Editor::move_lines_down, "b\na\nd\nc");
+        assert_eq!(heads(&editor), [at(1, 0), at(3, 1)]);
+    }
+
+    #[test]
+    fn move_lines_past_the_edges() {
+        // 第一行不能上移，最后一行不能下移，也不留下撤销记录
+        let mut editor = editor_selecting("a\nb\nc", at(0, 0), at(1, 1));
+        editor.move_lines_up();
+        assert_eq!(editor.get_text(), "a\nb\nc");
+        let mut editor = editor_with_cursors("a\nb\nc", &[at(0, 0), at(2, 0)]);
+        editor.move_lines_down();
+        assert_eq!(editor.get_text(), "a\nb\nc");
+        editor.type_char('x');
+        editor.undo();
+        editor.undo();
+        assert_eq!(editor.get_text(), "a\nb\nc");
+    }
+
+    #[test]
+    fn delete_lines() {
+        let mut editor = editor_selecting("a\nb\nc\nd", at(1, 0), at(2, 1));
+        check_undo(&mut editor, Editor::delete_lines, "a\nd");
+        assert_eq!(editor.get_cursor_position(), at(1, 1));
+
+        let mut editor = editor_with_cursors("a\nb\nc\nd", &[at(0, 0), at(2, 0)]);
+        check_undo(&mut editor, Editor::delete_lines, "b\nd");
+        assert_eq!(heads(&editor), [at(0, 0), at(1, 0)]);
+
+        // 删除最后一行时连同前一行的换行符
+        let mut editor = editor_at("a\nb\nc", at(2, 1));
+        check_undo(&mut editor, Editor::delete_lines, "a\nb");
+        assert_eq!(editor.get_cursor_position(), at(1, 1));
+    }
+
+    #[test]
+    fn join_lines() {
+        let mut editor = editor_at("a\n    b\nc", at(0, 0));
+        check_undo(&mut editor, Editor::join_lines, "a b\nc");
+        assert_eq!(editor.get_cursor_position(), at(0, 2));
+
+        let mut editor = editor_selecting("a\n  b\nc\nd", at(0, 0), at(2, 1));
+        check_undo(&mut editor, Editor::join_lines, "a b c\nd");
+
+        let mut editor = editor_with_cursors("a\nb\nc\nd", &[at(0, 0), at(2, 0)]);
+        check_undo(&mut editor, Editor::join_lines, "a b\nc d");
+        assert_eq!(heads(&editor), [at(0, 2), at(1, 2)]);
+
+        // 与空行合并时不加空格，最后一行没有可合并的行
+        let mut editor = editor_at("a\n\nb", at(0, 0));
+        check_undo(&mut editor, Editor::join_lines, "a\nb");
+        let mut editor = editor_at("a\nb", at(1, 0));
+        editor.join_lines();
+        assert_eq!(editor.get_text(), "a\nb");
+    }
+
+    #[test]
+    fn tab_pads_to_the_next_tab_stop() {
+        let mut editor = editor_at("ab", at(0, 1));
+        check_undo(&mut editor, Editor::move_cursor_tab, "a   b");
+        assert_eq!(editor.get_cursor_position(), at(0, 4));
+        editor.move_cursor_tab();
+        assert_eq!(editor.get_text(), "a       b");
+        // 制表位按显示列计算，宽字符占两列
+        let mut editor = editor_at("中x", at(0, 1));
+        editor.move_cursor_tab();
+        assert_eq!(editor.get_text(), "中  x");
+        assert_eq!(editor.cursor_visual_column(), 4);
+    }
+
+    #[test]
+    fn tab_and_shift_tab_over_lines() {
+        let mut editor = editor_selecting("a\n\n  b\nc", at(0, 0), at(2, 1));
+        check_undo(&mut editor, Editor::move_cursor_tab, "    a\n\n      b\nc");
+        let selection = editor.get_selection();
+        assert_eq!((selection.anchor, selection.head), (at(0, 4), at(2, 5)));
+        check_undo(&mut editor, Editor::dedent_lines, "a\n\n  b\nc");
+        let selection = editor.get_selection();
+        assert_eq!((selection.anchor, selection.head), (at(0, 0), at(2, 1)));
+    }
+
+    #[test]
+    fn hard_tabs() {
+        let mut editor = editor_selecting("a\nb", at(0, 0), at(1, 1));
+        editor.set_indent_config(IndentConfig::from_option("tabs-4").unwrap());
+        check_undo(&mut editor, Editor::move_cursor_tab, "\ta\n\tb");
+        let mut editor = editor_at("ab", at(0, 1));
+        editor.set_indent_config(IndentConfig::from_option("tabs-4").unwrap());
+        editor.move_cursor_tab();
+        assert_eq!(editor.get_text(), "a\tb");
+        assert_eq!(editor.cursor_visual_column(), 4);
+        // 减少缩进时一次删除一个制表符
+        let mut editor = editor_at("\t\ta", at(0, 3));
+        check_undo(&mut editor, Editor::dedent_lines, "\ta");
+        assert_eq!(editor.get_cursor_position(), at(0, 2));
+    }
+
+    #[test]
+    fn dedent_partial_indent() {
+        // 不足一级的缩进全部删除，多于一级的只删除一级
+        let mut editor = editor_with_cursors("  a\n      b\nc", &[at(0, 2), at(1, 6), at(2, 0)]);
+        check_undo(&mut editor, Editor::dedent_lines, "a\n  b\nc");
+        assert_eq!(heads(&editor), [at(0, 0), at(1, 2), at(2, 0)]);
+        // 光标在缩进之中时移到行首
+        let mut editor = editor_at("    a", at(0, 1));
+        editor.dedent_lines();
+        assert_eq!(editor.get_cursor_position(), at(0, 0));
+    }
+
+    #[test]
+    fn auto_close_pairs() {
+        for (ch, expected) in [('(', "()"), ('[', "[]"), ('{', "{}"), ('"', "\"\"")] {
+            let mut editor = Editor::new();
+            editor.type_char(ch);
+            assert_eq!(editor.get_text(), expected);
+            assert_eq!(editor.get_cursor_position(), at(0, 1));
+            // 在空白和闭合字符之前也补全
+            let mut editor = editor_at("f( )", at(0, 2));
+            editor.type_char(ch);
+            assert_eq!(editor.get_text(), format!("f({} )", expected));
+            let mut editor = editor_at("f()", at(0, 2));
+            editor.type_char(ch);
+            assert_eq!(editor.get_text(), format!("f({})", expected));
+        }
+    }
+
+    #[test]
+    fn step_over_closers() {
+        let mut editor = Editor::new();
+        for ch in "f(\"a\")".chars() {
+            editor.type_char(ch);
+        }
+        assert_eq!(editor.get_text(), "f(\"a\")");
+        assert_eq!(editor.get_cursor_position(), at(0, 6));
+        // 后面不是同一个闭合字符时照常输入
+        let mut editor = editor_at("a]", at(0, 1));
+        editor.type_char(')');
+        assert_eq!(editor.get_text(), "a)]");
+    }
+
+    #[test]
+    fn wrap_selection_in_pair() {
+        let mut editor = editor_selecting("let x = a + b;", at(0, 8), at(0, 13));
+        editor.type_char('(');
+        assert_eq!(editor.get_text(), "let x = (a + b);");
+        assert_eq!(editor.get_selected_text(), "a + b");
+        editor.type_char('"');
+        assert_eq!(editor.get_text(), "let x = (\"a + b\");");
+        editor.undo();
+        assert_eq!(editor.get_text(), "let x = (a + b);");
+        assert_eq!(editor.get_selected_text(), "a + b");
+
+        // 每个选区分别包裹
+        let mut editor = editor_selecting("ab x ab", at(0, 0), at(0, 2));
+        editor.add_next_occurrence();
+        editor.type_char('[');
+        assert_eq!(editor.get_text(), "[ab] x [ab]");
+        assert_eq!(editor.get_selected_text(), "ab\nab");
+    }
+
+    #[test]
+    fn backspace_deletes_empty_pair() {
+        for text in ["()", "[]", "{}", "\"\""] {
+            let mut editor = editor_at(&format!("a{}b", text), at(0, 2));
+            editor.move_cursor_backspace(1);
+            assert_eq!(editor.get_text(), "ab", "{text:?}");
+            assert_eq!(editor.get_cursor_position(), at(0, 1));
+        }
+        // 不成对或不为空时只删除一个字符
+        let mut editor = editor_at("(]", at(0, 1));
+        editor.move_cursor_backspace(1);
+        assert_eq!(editor.get_text(), "]");
+        let mut editor = editor_at("(x)", at(0, 2));
+        editor.move_cursor_backspace(1);
+        assert_eq!(editor.get_text(), "()");
+    }
+
+    #[test]
+    fn no_auto_close_next_to_words() {
+        // 单词之前不补全
+        for ch in ['(', '[', '{', '"'] {
+            let mut editor = editor_at("abc", at(0, 0));
+            editor.type_char(ch);
+            assert_eq!(editor.get_text(), format!("{}abc", ch));
+        }
+        // 引号在单词之后也不补全，括号可以
+        let mut editor = editor_at("abc", at(0, 3));
+        editor.type_char('"');
+        assert_eq!(editor.get_text(), "abc\"");
+        let mut editor = editor_at("abc", at(0, 3));
+        editor.type_char('(');
+        assert_eq!(editor.get_text(), "abc()");
+    }
+
+    #[test]
+    fn toggle_line_comment_with_mixed_indentation() {
+        let text = "fn f() {\n    a;\n\n  b;\n}";
+        let mut editor = editor_selecting(text, at(1, 0), at(3, 4));
+        // 注释符号对齐到最浅的缩进，中间的空行不变
+        check_undo(
+            &mut editor,
+            Editor::toggle_line_comment,
+            "fn f() {\n  //   a;\n\n  // b;\n}",
+        );
+        check_undo(&mut editor, Editor::toggle_line_comment, text);
+
+        // 有一行未注释时全部加上注释
+        let mut editor = editor_selecting("// a\nb", at(0, 0), at(1, 1));
+        editor.toggle_line_comment();
+        assert_eq!(editor.get_text(), "// // a\n// b");
+    }
+
+    #[test]
+    fn toggle_line_comment_on_blank_lines() {
+        let mut editor = editor_at("a\n\nb", at(1, 0));
+        check_undo(&mut editor, Editor::toggle_line_comment, "a\n// \nb");
+        check_undo(&mut editor, Editor::toggle_line_comment, "a\n\nb");
+    }
+
+    #[test]
+    fn toggle_block_comment() {
+        let mut editor = editor_at("    let a = 1;", at(0, 8));
+        check_undo(
+            &mut editor,
+            Editor::toggle_block_comment,
+            "    /* let a = 1; */",
+        );
+        assert_eq!(editor.get_cursor_position(), at(0, 11));
+        check_undo(&mut editor, Editor::toggle_block_comment, "    let a = 1;");
+        assert_eq!(editor.get_cursor_position(), at(0, 8));
+
+        let mut editor = editor_selecting("let a = 1;", at(0, 8), at(0, 9));
+        check_undo(
+            &mut editor,
+            Editor::toggle_block_comment,
+            "let a = /* 1 */;",
+        );
+        assert_eq!(editor.get_selected_text(), "1");
+    }
+
+    #[test]
+    fn markdown_falls_back_to_block_comments() {
+        let mut editor = editor_at("# T\nabc", at(1, 1));
+        editor.set_language(Language::Markdown);
+        check_undo(
+            &mut editor,
+            Editor::toggle_line_comment,
+            "# T\n<!-- abc -->",
+        );
+        check_undo(&mut editor, Editor::toggle_line_comment, "# T\nabc");
+
+        let mut editor = editor_selecting("a\nb", at(0, 0), at(1, 1));
+        editor.set_language(Language::Markdown);
+        check_undo(&mut editor, Editor::toggle_line_comment, "<!-- a\nb -->");
+    }
+
+    fn matching(editor: &Editor) -> Vec<Position> {
+        editor.matching_brackets(&editor.parse_text())
+    }
+
+    #[test]
+    fn brackets_in_strings_and_comments_are_ignored() {
+        for (text, open, close) in [
+            ("fn f() { let s = \"(}]\"; }", at(0, 7), at(0, 24)),
+            ("fn f() {\n    // ) ] }\n}", at(0, 7), at(2, 0)),
+            ("f(/* ( } */ a)", at(0, 1), at(0, 13)),
+            ("f(a /* ) */,\n  b)", at(0, 1), at(1, 3)),
+        ] {
+            let editor = editor_at(text, open);
+            assert_eq!(matching(&editor), [open, close], "{text:?}");
+            let editor = editor_at(text, close);
+            assert_eq!(matching(&editor), [close, open], "{text:?}");
+        }
+        // 光标在字符串中的括号旁时没有配对
+        let editor = editor_at("let s = \"(\";", at(0, 9));
+        assert!(matching(&editor).is_empty());
+    }
+
+    #[test]
+    fn jump_to_matching_bracket() {
+        let text = "fn f() {\n    g(\"}\");\n}";
+        // 光标在开括号之前时跳到闭括号之前，再跳回来
+        let mut editor = editor_at(text, at(0, 7));
+        editor.jump_to_matching_bracket();
+        assert_eq!(editor.get_cursor_position(), at(2, 0));
+        editor.jump_to_matching_bracket();
+        assert_eq!(editor.get_cursor_position(), at(0, 7));
+        // 光标在闭括号之后时跳到开括号之后
+        let mut editor = editor_at(text, at(2, 1));
+        editor.jump_to_matching_bracket();
+        assert_eq!(editor.get_cursor_position(), at(0, 8));
+        let mut editor = editor_at(text, at(1, 10));
+        editor.jump_to_matching_bracket();
+        assert_eq!(editor.get_cursor_position(), at(1, 6));
+        // 不在括号旁时不动
+        let mut editor = editor_at(text, at(1, 8));
+        editor.jump_to_matching_bracket();
+        assert_eq!(editor.get_cursor_position(), at(1, 8));
+    }
+
+    const FOLDED: &str = "fn a() {\n    1;\n    2;\n}\nx";
+
+    /// 被折叠隐藏的行区间 (首行, 末行之后)
+    fn folded(editor: &Editor) -> Vec<(usize, usize)> {
+        editor
+            .folded_lines()
+            .into_iter()
+            .map(|r| (r.start, r.end))
+            .collect()
+    }
+
+    /// 折叠了 FOLDED 第 0 行的函数体(隐藏第 1、2 行)的编辑器
+    fn folded_editor(pos: Position) -> Editor {
+        let mut editor = editor_at(FOLDED, pos);
+        editor.toggle_fold(0);
+        assert_eq!(folded(&editor), [(1, 3)]);
+        editor
+    }
+
+    #[test]
+    fn moves_skip_folded_lines() {
+        let mut editor = folded_editor(at(0, 0));
+        editor.move_cursor_down(false);
+        assert_eq!(editor.get_cursor_position(), at(3, 0));
+        editor.move_cursor_up(false);
+        assert_eq!(editor.get_cursor_position(), at(0, 0));
+
+        let mut editor = folded_editor(at(0, 8));
+        editor.move_cursor_right(false);
+        assert_eq!(editor.get_cursor_position(), at(3, 0));
+        editor.move_cursor_left(false);
+        assert_eq!(editor.get_cursor_position(), at(0, 8));
+
+        let mut editor = folded_editor(at(0, 0));
+        editor.move_cursor_page_down(2, false);
+        assert_eq!(editor.get_cursor_position(), at(4, 0));
+        editor.move_cursor_page_up(1, false);
+        assert_eq!(editor.get_cursor_position(), at(3, 0));
+        editor.move_cursor_page_up(5, false);
+        assert_eq!(editor.get_cursor_position(), at(0, 0));
+        // 移动不会展开折叠
+        assert_eq!(folded(&editor), [(1, 3)]);
+    }
+
+    #[test]
+    fn edits_above_a_fold_shift_it() {
+        let mut editor = folded_editor(at(0, 0));
+        editor.insert_text("// c\n");
+        assert_eq!(folded(&editor), [(2, 4)]);
+        editor.undo();
+        assert_eq!(folded(&editor), [(1, 3)]);
+        // 在首行内、开括号之前的修改也只平移折叠
+        editor.set_cursor_position(at(0, 3));
+        editor.insert_text("bc");
+        assert_eq!(folded(&editor), [(1, 3)]);
+    }
+
+    #[test]
+    fn deleting_the_opener_drops_the_fold() {
+        let mut editor = folded_editor(at(0, 7));
+        editor.select_to(at(0, 8));
+        editor.move_cursor_backspace(1);
+        assert_eq!(editor.get_text(), "fn a() \n    1;\n    2;\n}\nx");
+        // 重新输入开括号后不会恢复旧的折叠
+        editor.insert_text("{");
+        assert_eq!(editor.get_text(), FOLDED);
+        assert!(folded(&editor).is_empty());
+    }
+
+    #[test]
+    fn fold_multi_line_comments() {
+        let mut editor = editor_at("/* a\n b\n c */\nx", at(0, 0));
+        editor.toggle_fold(0);
+        assert_eq!(folded(&editor), [(1, 3)]);
+        editor.move_cursor_down(false);
+        assert_eq!(editor.get_cursor_position(), at(3, 0));
+        editor.toggle_fold(0);
+        assert!(folded(&editor).is_empty());
+    }
+
+    /// 在 pos 处换行后的文本和光标，一次撤销恢复原文
+    fn newline_at(text: &str, pos: Position, indent: &str) -> (String, Position) {
+        let mut editor = editor_at(text, pos);
+        editor.set_indent_config(IndentConfig::from_option(indent).unwrap());
+        editor.move_cursor_enter();
+        let result = (editor.get_text(), editor.get_cursor_position());
+        editor.undo();
+        assert_eq!(editor.get_text(), text);
+        result
+    }
+
+    #[test]
+    fn newline_copies_indentation() {
+        for (text, pos, expected, cursor) in [
+            (
+                "    let a = 1;",
+                at(0, 14),
+                "    let a = 1;\n    ",
+                at(1, 4),
+            ),
+            ("\t\tx", at(0, 3), "\t\tx\n\t\t", at(1, 2)),
+            ("  a b", at(0, 3), "  a\n   b", at(1, 2)),
+            ("x", at(0, 1), "x\n", at(1, 0)),
+        ] {
+            assert_eq!(
+                newline_at(text, pos, "spaces-4"),
+                (expected.to_string(), cursor),
+                "{text:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn newline_indents_after_openers() {
+        for (text, pos, expected, cursor) in [
+            ("foo(", at(0, 4), "foo(\n    ", at(1, 4)),
+            ("  v = [", at(0, 7), "  v = [\n      ", at(1, 6)),
+            ("  if x { ", at(0, 9), "  if x { \n      ", at(1, 6)),
+            ("f(a, b", at(0, 4), "f(a,\n b", at(1, 0)),
+        ] {
+            assert_eq!(
+                newline_at(text, pos, "spaces-4"),
+                (expected.to_string(), cursor),
+                "{text:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn newline_splits_bracket_pairs() {
+        // 闭括号放在单独的一行，缩进与开括号所在的行相同
+        for (text, pos, expected, cursor) in [
+            ("  if x {}", at(0, 8), "  if x {\n      \n  }", at(1, 6)),
+            ("f()", at(0, 2), "f(\n    \n)", at(1, 4)),
+            ("  [];", at(0, 3), "  [\n      \n  ];", at(1, 6)),
+        ] {
+            assert_eq!(
+                newline_at(text, pos, "spaces-4"),
+                (expected.to_string(), cursor),
+                "{text:?}"
+            );
+        }
+        // 括号不配对时不拆分
+        assert_eq!(
+            newline_at("f(]", at(0, 2), "spaces-4"),
+            ("f(\n    ]".to_string(), at(1, 4))
+        );
+    }
+
+    #[test]
+    fn newline_with_hard_tabs() {
+        assert_eq!(
+            newline_at("\tif x {}", at(0, 7), "tabs-4"),
+            ("\tif x {\n\t\t\n\t}".to_string(), at(1, 2))
+        );
+        assert_eq!(
+            newline_at("\tf(", at(0, 3), "tabs-8"),
+            ("\tf(\n\t\t".to_string(), at(1, 2))
+        );
+    }
+
+    #[test]
+    fn typing_after_find_next_is_a_separate_undo_step() {
+        let mut editor = editor_at("foo bar\nbar", at(0, 0));
+        editor.type_char('a');
+        editor.set_search_query(Some(SearchQuery {
+            pattern: "bar".to_string(),
+            ..SearchQuery::default()
+        }));
+        assert!(editor.find_next(false));
+        editor.type_char('x');
+        editor.type_char('y');
+        assert!(editor.find_next(false));
+        editor.type_char('z');
+        assert_eq!(editor.get_text(), "afoo xy\nz");
+        editor.undo();
+        assert_eq!(editor.get_text(), "afoo xy\nbar");
+        // 替换选区的输入不与之后的输入合并
+        editor.undo();
+        assert_eq!(editor.get_text(), "afoo x\nbar");
+        editor.undo();
+        assert_eq!(editor.get_text(), "afoo bar\nbar");
+        editor.undo();
+        assert_eq!(editor.get_text(), "foo bar\nbar");
+    }
+}
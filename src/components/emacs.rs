@@ -0,0 +1,476 @@
+use super::command::{EditorCommand, Motion};
+use super::editor_new::{line_len, Editor};
+use dioxus::prelude::*;
+use std::ops::Range;
+use std::path::PathBuf;
+
+const KILL_RING_LIMIT: usize = 60;
+
+/// handle_key 处理的 Ctrl 组合键和 Alt 组合键，它们不会交给默认绑定
+pub const CTRL_KEYS: &[&str] = &[
+    "f", "b", "n", "p", "a", "e", "x", " ", "g", "k", "w", "y", "/", "_",
+];
+pub const ALT_KEYS: &[&str] = &["f", "b", "<", ">", "w", "y"];
+
+/// 上一个命令的类型，连续剪切时合并到同一条，粘贴之后才能 M-y
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum LastCommand {
+    #[default]
+    Other,
+    Kill,
+    Yank,
+}
+
+/// Emacs 风格的按键绑定，未绑定的按键交给默认绑定处理
+#[derive(Debug, Default)]
+pub struct Emacs {
+    /// 剪切环，最新的在末尾
+    kill_ring: Vec<String>,
+    /// 上次粘贴的内容在剪切环中的下标
+    yank_index: usize,
+    last: LastCommand,
+    /// C-space 设置的标记是否有效，有效时移动光标会扩展选区
+    mark: bool,
+    /// 已按下 C-x，等待下一个按键
+    prefix: bool,
+    /// 正在输入要写入的文件名(没有文件名时按下 C-x C-s)
+    file_prompt: Option<String>,
+    message: String,
+}
+
+impl Emacs {
+    /// 状态栏文本
+    pub fn status(&self) -> &str {
+        if self.prefix {
+            "C-x-"
+        } else {
+            &self.message
+        }
+    }
+
+    /// 处理一次按键，返回是否已处理
+    pub fn handle_key(&mut self, editor: &mut Editor, key: &Key, modifiers: Modifiers) -> bool {
+        // 单独按下修饰键不影响 C-x 前缀和连续剪切
+        if matches!(key, Key::Control | Key::Shift | Key::Alt | Key::Meta) {
+            return false;
+        }
+        if self.file_prompt.is_some() {
+            self.file_prompt_key(editor, key, modifiers);
+            return true;
+        }
+        self.message.clear();
+        let last = std::mem::take(&mut self.last);
+        let prefix = std::mem::take(&mut self.prefix);
+        let Key::Character(c) = key else {
+            return self.unhandled(editor);
+        };
+        let c = c.to_lowercase();
+        let ctrl = modifiers.ctrl();
+        let alt = modifiers.alt();
+        // Ctrl+Shift+字母(如命令面板)留给默认绑定
+        if ctrl && modifiers.shift() && c.chars().all(char::is_alphabetic) {
+            return self.unhandled(editor);
+        }
+        if prefix {
+            match c.as_str() {
+                "s" if ctrl && editor.file_path().is_none() => {
+                    self.file_prompt = Some(String::new());
+                    self.message = "写入文件：".to_string();
+                }
+                "s" if ctrl => self.save(editor, None),
+                _ => self.message = format!("C-x {} 未定义", c),
+            }
+            return true;
+        }
+        let movement = match c.as_str() {
+            "f" if ctrl => Some(Motion::Right),
+            "b" if ctrl => Some(Motion::Left),
+            "n" if ctrl => Some(Motion::Down),
+            "p" if ctrl => Some(Motion::Up),
+            "a" if ctrl => Some(Motion::LineBegin),
+            "e" if ctrl => Some(Motion::LineEnd),
+            "f" if alt => Some(Motion::WordRight),
+            "b" if alt => Some(Motion::WordLeft),
+            "<" if alt => Some(Motion::DocumentStart),
+            ">" if alt => Some(Motion::DocumentEnd),
+            _ => None,
+        };
+        if let Some(motion) = movement {
+            editor.execute(EditorCommand::Move {
+                motion,
+                select: self.mark,
+            });
+            return true;
+        }
+        match c.as_str() {
+            "x" if ctrl => self.prefix = true,
+            " " if ctrl => {
+                self.mark = true;
+                collapse_selection(editor);
+                self.message = "已设置标记".to_string();
+            }
+            "g" if ctrl => {
+                self.deactivate_mark(editor);
+                self.message = "已取消".to_string();
+            }
+            "k" if ctrl => self.kill_line(editor, last),
+            "w" if ctrl => {
+                self.mark = false;
+                self.kill(editor, last);
+            }
+            "w" if alt => {
+                let range = self.region(editor);
+                let text = editor.rope().slice(range).to_string();
+                self.push_kill(text);
+                self.deactivate_mark(editor);
+            }
+            "y" if ctrl => self.yank(editor),
+            "y" if alt => {
+                if last == LastCommand::Yank {
+                    self.yank_pop(editor);
+                } else {
+                    self.message = "上一个命令不是粘贴".to_string();
+                }
+            }
+            "/" | "_" if ctrl => {
+                editor.execute(EditorCommand::Undo);
+            }
+            _ => return self.unhandled(editor),
+        }
+        true
+    }
+
+    /// 在状态栏输入文件名，回车写入，C-g 或 Esc 取消
+    fn file_prompt_key(&mut self, editor: &mut Editor, key: &Key, modifiers: Modifiers) {
+        let Some(input) = &mut self.file_prompt else {
+            return;
+        };
+        match key {
+            Key::Character(c) if c == "g" && modifiers.ctrl() => {
+                self.file_prompt = None;
+                self.message = "已取消".to_string();
+                return;
+            }
+            Key::Escape => {
+                self.file_prompt = None;
+                self.message = "已取消".to_string();
+                return;
+            }
+            Key::Character(c) if !modifiers.ctrl() && !modifiers.alt() => input.push_str(c),
+            Key::Backspace => {
+                input.pop();
+            }
+            Key::Enter if !input.trim().is_empty() => {
+                let path = PathBuf::from(input.trim());
+                self.file_prompt = None;
+                self.save(editor, Some(path));
+                return;
+            }
+            _ => {}
+        }
+        self.message = format!("写入文件：{}", input);
+    }
+
+    /// 写入文件，未给出路径时写入当前文件
+    fn save(&mut self, editor: &mut Editor, path: Option<PathBuf>) {
+        match editor.save(path) {
+            Ok(path) => self.message = format!("已写入 {}", path.display()),
+            Err(err) => self.message = format!("写入失败：{}", err),
+        }
+    }
+
+    /// 未绑定的按键：输入或其他编辑之前取消标记
+    fn unhandled(&mut self, editor: &mut Editor) -> bool {
+        if self.mark {
+            self.deactivate_mark(editor);
+        }
+        false
+    }
+
+    fn deactivate_mark(&mut self, editor: &mut Editor) {
+        self.mark = false;
+        collapse_selection(editor);
+    }
+
+    /// 标记和光标之间的字符区间
+    fn region(&self, editor: &Editor) -> Range<usize> {
+        let rope = editor.rope();
+        let selection = editor.get_selection();
+        selection.start().char_offset(&rope)..selection.end().char_offset(&rope)
+    }
+
+    /// C-k：剪切到行尾，已在行尾时剪切换行符
+    fn kill_line(&mut self, editor: &mut Editor, last: LastCommand) {
+        self.deactivate_mark(editor);
+        let rope = editor.rope();
+        let pos = editor.get_cursor_position();
+        if pos.col < line_len(&rope, pos.line) {
+            editor.execute(EditorCommand::Move {
+                motion: Motion::LineEnd,
+                select: true,
+            });
+            self.kill(editor, last);
+        } else if pos.line + 1 < rope.len_lines() {
+            // 向后删除换行符，下一行被折叠时也只删除换行符
+            self.add_kill("\n".to_string(), last);
+            editor.execute(EditorCommand::Delete);
+            self.last = LastCommand::Kill;
+        }
+    }
+
+    /// 剪切选区并存入剪切环
+    fn kill(&mut self, editor: &mut Editor, last: LastCommand) {
+        let range = self.region(editor);
+        if range.is_empty() {
+            return;
+        }
+        let text = editor.rope().slice(range).to_string();
+        self.add_kill(text, last);
+        editor.execute(EditorCommand::Cut);
+        self.last = LastCommand::Kill;
+    }
+
+    /// 存入剪切环，紧跟在上一次剪切之后时合并
+    fn add_kill(&mut self, text: String, last: LastCommand) {
+        match self.kill_ring.last_mut() {
+            Some(top) if last == LastCommand::Kill => top.push_str(&text),
+            _ => self.push_kill(text),
+        }
+    }
+
+    fn push_kill(&mut self, text: String) {
+        if text.is_empty() {
+            return;
+        }
+        self.kill_ring.push(text);
+        if self.kill_ring.len() > KILL_RING_LIMIT {
+            self.kill_ring.remove(0);
+        }
+    }
+
+    /// C-y：在光标处粘贴最近一次剪切的内容
+    fn yank(&mut self, editor: &mut Editor) {
+        let Some(index) = self.kill_ring.len().checked_sub(1) else {
+            self.message = "剪切环为空".to_string();
+            return;
+        };
+        self.deactivate_mark(editor);
+        self.insert_yank(editor, index);
+    }
+
+    /// M-y：撤销刚才的粘贴，换成剪切环中更早的一条
+    fn yank_pop(&mut self, editor: &mut Editor) {
+        let len = self.kill_ring.len();
+        let index = (self.yank_index + len - 1) % len;
+        editor.execute(EditorCommand::Undo);
+        self.insert_yank(editor, index);
+    }
+
+    fn insert_yank(&mut self, editor: &mut Editor, index: usize) {
+        editor.execute(EditorCommand::Paste(self.kill_ring[index].clone()));
+        self.yank_index = index;
+        self.last = LastCommand::Yank;
+    }
+}
+
+/// 把选区收起到光标处，按相对光标的移动执行以便录入宏
+fn collapse_selection(editor: &mut Editor) {
+    let selection = editor.get_selection();
+    if selection.is_empty() {
+        return;
+    }
+    let motion = if selection.head >= selection.anchor {
+        Motion::Right
+    } else {
+        Motion::Left
+    };
+    editor.execute(EditorCommand::Move {
+        motion,
+        select: false,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::position::Position;
+
+    /// 按下以空格分隔的按键，如 `C-k M-y C-space`；未处理的按键作为输入
+    fn press(emacs: &mut Emacs, editor: &mut Editor, keys: &str) {
+        for key in keys.split_whitespace() {
+            let (modifiers, name) = match key.split_at(key.len().min(2)) {
+                ("C-", name) => (Modifiers::CONTROL, name),
+                ("M-", name) => (Modifiers::ALT, name),
+                _ => (Modifiers::empty(), key),
+            };
+            let name = if name == "space" { " " } else { name };
+            if !emacs.handle_key(editor, &Key::Character(name.to_string()), modifiers) {
+                editor.execute(EditorCommand::InsertText(name.to_string()));
+            }
+        }
+    }
+
+    fn run(text: &str, keys: &str) -> (Editor, Emacs) {
+        let mut editor = Editor::with_text(text);
+        let mut emacs = Emacs::default();
+        press(&mut emacs, &mut editor, keys);
+        (editor, emacs)
+    }
+
+    fn cursor(editor: &Editor) -> (usize, usize) {
+        let pos = editor.get_cursor_position();
+        (pos.line, pos.col)
+    }
+
+    #[test]
+    fn ctrl_a_goes_to_column_zero() {
+        let (editor, _) = run("  ab", "C-e C-a");
+        assert_eq!(cursor(&editor), (0, 0));
+        let (editor, _) = run("  ab", "C-f C-f C-a C-a");
+        assert_eq!(cursor(&editor), (0, 0));
+    }
+
+    #[test]
+    fn consecutive_kills_are_merged() {
+        let (mut editor, mut emacs) = run("ab\ncd", "C-k C-k");
+        assert_eq!(editor.get_text(), "cd");
+        assert_eq!(emacs.kill_ring, ["ab\n"]);
+        press(&mut emacs, &mut editor, "C-e C-y");
+        assert_eq!(editor.get_text(), "cdab\n");
+        assert_eq!(cursor(&editor), (1, 0));
+    }
+
+    #[test]
+    fn kill_line_at_end_of_document() {
+        let (editor, emacs) = run("ab", "C-e C-k");
+        assert_eq!(editor.get_text(), "ab");
+        assert!(emacs.kill_ring.is_empty());
+    }
+
+    #[test]
+    fn yank_pop_cycles_the_kill_ring() {
+        let (mut editor, mut emacs) = run("a\nb\nc", "C-k C-n C-k");
+        assert_eq!(emacs.kill_ring, ["a", "b"]);
+        assert_eq!(editor.get_text(), "\n\nc");
+        press(&mut emacs, &mut editor, "C-y");
+        assert_eq!(editor.get_text(), "\nb\nc");
+        press(&mut emacs, &mut editor, "M-y");
+        assert_eq!(editor.get_text(), "\na\nc");
+        assert_eq!(cursor(&editor), (1, 1));
+        press(&mut emacs, &mut editor, "M-y");
+        assert_eq!(editor.get_text(), "\nb\nc");
+        // 撤销只撤销最后一次粘贴
+        press(&mut emacs, &mut editor, "C-/");
+        assert_eq!(editor.get_text(), "\n\nc");
+    }
+
+    #[test]
+    fn yank_pop_needs_a_yank() {
+        let (editor, emacs) = run("ab", "C-y");
+        assert_eq!(emacs.status(), "剪切环为空");
+        assert_eq!(editor.get_text(), "ab");
+        let (editor, emacs) = run("ab", "C-k C-y C-f M-y");
+        assert_eq!(emacs.status(), "上一个命令不是粘贴");
+        assert_eq!(editor.get_text(), "ab");
+    }
+
+    #[test]
+    fn mark_extends_the_selection() {
+        let (mut editor, mut emacs) = run("one two", "C-space C-f M-f");
+        let selection = editor.get_selection();
+        assert_eq!(
+            (selection.anchor, selection.head),
+            (Position::new(0, 0), Position::new(0, 3))
+        );
+        press(&mut emacs, &mut editor, "C-g");
+        assert!(editor.get_selection().is_empty());
+        assert_eq!(cursor(&editor), (0, 3));
+        assert!(!emacs.mark);
+        // 取消标记后移动不再扩展选区
+        press(&mut emacs, &mut editor, "C-f");
+        assert!(editor.get_selection().is_empty());
+    }
+
+    #[test]
+    fn copy_and_kill_region() {
+        let (mut editor, mut emacs) = run("one two", "C-space M-f M-w");
+        assert_eq!(editor.get_text(), "one two");
+        assert!(editor.get_selection().is_empty());
+        press(&mut emacs, &mut editor, "C-e C-y");
+        assert_eq!(editor.get_text(), "one twoone");
+        // 标记在光标之后时同样剪切标记和光标之间的内容
+        press(&mut emacs, &mut editor, "C-space C-a C-w");
+        assert_eq!(editor.get_text(), "");
+        assert_eq!(emacs.kill_ring, ["one", "one twoone"]);
+    }
+
+    #[test]
+    fn typing_deactivates_the_mark() {
+        let (editor, emacs) = run("ab", "C-space C-f x");
+        assert!(!emacs.mark);
+        assert_eq!(editor.get_text(), "axb");
+    }
+
+    #[test]
+    fn bound_keys_are_handled() {
+        let handles = |key: &str, modifiers| {
+            let mut editor = Editor::with_text("ab");
+            let key = Key::Character(key.to_string());
+            Emacs::default().handle_key(&mut editor, &key, modifiers)
+        };
+        for key in CTRL_KEYS {
+            assert!(handles(key, Modifiers::CONTROL), "C-{}", key);
+        }
+        for key in ALT_KEYS {
+            assert!(handles(key, Modifiers::ALT), "M-{}", key);
+        }
+        assert!(!handles("z", Modifiers::CONTROL));
+        assert!(!handles("a", Modifiers::CONTROL | Modifiers::SHIFT));
+        assert!(!handles("x", Modifiers::ALT));
+    }
+
+    #[test]
+    fn save_without_a_file_prompts_for_one() {
+        let path = std::env::temp_dir().join(format!("editor-x-emacs-{}.txt", std::process::id()));
+        let (mut editor, mut emacs) = run("ab", "C-x C-s");
+        assert_eq!(emacs.status(), "写入文件：");
+        // 输入的字符进入文件名而不是文本
+        for c in path.display().to_string().chars() {
+            assert!(emacs.handle_key(
+                &mut editor,
+                &Key::Character(c.to_string()),
+                Modifiers::empty()
+            ));
+        }
+        emacs.handle_key(&mut editor, &Key::Backspace, Modifiers::empty());
+        press(&mut emacs, &mut editor, "t");
+        assert!(emacs.status().ends_with(&path.display().to_string()));
+        assert!(emacs.handle_key(&mut editor, &Key::Enter, Modifiers::empty()));
+        assert_eq!(editor.get_text(), "ab");
+        assert_eq!(editor.file_path(), Some(path.as_path()));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "ab");
+        assert!(emacs.status().starts_with("已写入"));
+
+        // 已有文件名时直接写入
+        press(&mut emacs, &mut editor, "c C-x C-s");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "cab");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn cancel_the_file_prompt() {
+        let (mut editor, mut emacs) = run("ab", "C-x C-s x C-g");
+        assert_eq!(emacs.status(), "已取消");
+        press(&mut emacs, &mut editor, "y");
+        assert_eq!(editor.get_text(), "yab");
+        assert_eq!(editor.file_path(), None);
+
+        let (mut editor, mut emacs) = run("ab", "C-x C-s");
+        emacs.handle_key(&mut editor, &Key::Escape, Modifiers::empty());
+        // 文件名为空时回车不写入
+        press(&mut emacs, &mut editor, "C-x C-s");
+        emacs.handle_key(&mut editor, &Key::Enter, Modifiers::empty());
+        assert_eq!(emacs.status(), "写入文件：");
+        assert_eq!(editor.file_path(), None);
+    }
+}
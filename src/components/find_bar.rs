@@ -0,0 +1,159 @@
+use super::editor_new::Editor;
+use super::search::SearchQuery;
+use dioxus::prelude::*;
+
+#[derive(Props, PartialEq, Clone)]
+pub struct FindBarProps {
+    editor: Signal<Editor>,
+    /// 是否显示替换输入框(Ctrl+H)
+    replace: bool,
+    on_close: EventHandler<()>,
+}
+
+/// 查找替换栏(Ctrl+F / Ctrl+H)，Enter 查找下一个，Shift+Enter 查找上一个
+#[component]
+pub fn FindBar(props: FindBarProps) -> Element {
+    let mut editor = props.editor;
+    // 选中的单行文本作为初始查找内容
+    let mut query = use_signal(|| {
+        let selected = editor.peek().get_selected_text();
+        SearchQuery {
+            pattern: if selected.contains('\n') {
+                String::new()
+            } else {
+                selected
+            },
+            ..Default::default()
+        }
+    });
+    let mut replace_text = use_signal(String::new);
+
+    use_effect(move || {
+        let query = query();
+        editor.with_mut(|e| e.set_search_query(Some(query)));
+    });
+    use_drop(move || editor.with_mut(|e| e.set_search_query(None)));
+
+    let status = editor.with(|e| match e.search_matches() {
+        Err(_) => "无效的正则表达式".to_string(),
+        Ok([]) => "无结果".to_string(),
+        Ok(matches) => match e.current_match_index() {
+            Some(i) => format!("第 {} 项，共 {} 项", i + 1, matches.len()),
+            None => format!("共 {} 项", matches.len()),
+        },
+    });
+
+    let on_find_keydown = move |e: Event<KeyboardData>| match e.key() {
+        Key::Enter => {
+            editor.with_mut(|editorx| editorx.find_next(e.modifiers().shift()));
+            e.prevent_default();
+        }
+        Key::Escape => props.on_close.call(()),
+        _ => {}
+    };
+    let on_replace_keydown = move |e: Event<KeyboardData>| match e.key() {
+        Key::Enter if e.modifiers().ctrl() => {
+            editor.with_mut(|editorx| editorx.replace_all(&replace_text()));
+            e.prevent_default();
+        }
+        Key::Enter => {
+            editor.with_mut(|editorx| editorx.replace_next(&replace_text()));
+            e.prevent_default();
+        }
+        Key::Escape => props.on_close.call(()),
+        _ => {}
+    };
+
+    let toggle_style = |active: bool| {
+        if active {
+            "margin-left: 4px; background: #cde; border: 1px solid #89a;"
+        } else {
+            "margin-left: 4px; border: 1px solid transparent;"
+        }
+    };
+
+    rsx! {
+        div {
+            style: "position: fixed; top: 48px; right: 16px; z-index: 10; background: #f0f0f0; border: 1px solid #ddd; padding: 4px; font-size: 14px;",
+            div {
+                style: "display: flex; align-items: center;",
+                input {
+                    style: "width: 200px;",
+                    placeholder: "查找",
+                    value: "{query().pattern}",
+                    oninput: move |e| query.with_mut(|q| q.pattern = e.value()),
+                    onkeydown: on_find_keydown,
+                    onmounted: move |e| {
+                        spawn(async move {
+                            let _ = e.set_focus(true).await;
+                        });
+                    },
+                }
+                button {
+                    style: toggle_style(query().case_sensitive),
+                    title: "区分大小写",
+                    onclick: move |_| query.with_mut(|q| q.case_sensitive = !q.case_sensitive),
+                    "Aa"
+                }
+                button {
+                    style: toggle_style(query().whole_word),
+                    title: "全字匹配",
+                    onclick: move |_| query.with_mut(|q| q.whole_word = !q.whole_word),
+                    "ab"
+                }
+                button {
+                    style: toggle_style(query().regex),
+                    title: "使用正则表达式",
+                    onclick: move |_| query.with_mut(|q| q.regex = !q.regex),
+                    ".*"
+                }
+                span { style: "margin: 0 8px; min-width: 100px;", "{status}" }
+                button {
+                    title: "上一个 (Shift+Enter)",
+                    onclick: move |_| {
+                        editor.with_mut(|e| e.find_next(true));
+                    },
+                    "↑"
+                }
+                button {
+                    title: "下一个 (Enter)",
+                    onclick: move |_| {
+                        editor.with_mut(|e| e.find_next(false));
+                    },
+                    "↓"
+                }
+                button {
+                    title: "关闭 (Escape)",
+                    onclick: move |_| props.on_close.call(()),
+                    "×"
+                }
+            }
+            if props.replace {
+                div {
+                    style: "display: flex; align-items: center; margin-top: 4px;",
+                    input {
+                        style: "width: 200px;",
+                        placeholder: "替换",
+                        value: "{replace_text}",
+                        oninput: move |e| replace_text.set(e.value()),
+                        onkeydown: on_replace_keydown,
+                    }
+                    button {
+                        style: "margin-left: 4px;",
+                        title: "替换 (Enter)",
+                        onclick: move |_| editor.with_mut(|e| e.replace_next(&replace_text())),
+                        "替换"
+                    }
+                    button {
+                        style: "margin-left: 4px;",
+                        title: "全部替换 (Ctrl+Enter)",
+                        onclick: move |_| {
+                            editor.with_mut(|e| e.replace_all(&replace_text()));
+                        },
+                        "全部替换"
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use super::editor_new::Editor;
+use super::position::{Offset, Position};
+use dioxus::prelude::*;
+
+/// 跳转目标，行列从 1 开始，偏移量从 0 开始
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum GoToTarget {
+    Line(usize, Option<usize>),
+    Offset(Offset),
+}
+
+impl GoToTarget {
+    /// 解析 `行`、`行:列` 或 `:偏移量`；偏移量默认按字符计，
+    /// `:b偏移量` 按 UTF-8 字节(编译器诊断)，`:u偏移量` 按 UTF-16 码元(LSP)
+    fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        if let Some(offset) = input.strip_prefix(':') {
+            let offset = offset.trim();
+            let (unit, value): (fn(usize) -> Offset, &str) =
+                if let Some(value) = offset.strip_prefix('b') {
+                    (Offset::Utf8, value)
+                } else if let Some(value) = offset.strip_prefix('u') {
+                    (Offset::Utf16, value)
+                } else {
+                    (Offset::Char, offset)
+                };
+            return value
+                .trim()
+                .parse()
+                .ok()
+                .map(|n| GoToTarget::Offset(unit(n)));
+        }
+        let (line, col) = match input.split_once(':') {
+            Some((line, col)) => (line, Some(col.trim().parse().ok()?)),
+            None => (input, None),
+        };
+        Some(GoToTarget::Line(line.trim().parse().ok()?, col))
+    }
+
+    /// 移动光标到目标处，超出范围时取最近的有效位置
+    fn go_to(self, editor: &mut Editor) {
+        match self {
+            GoToTarget::Line(line, col) => editor.go_to_position(Position::new(
+                line.saturating_sub(1),
+                col.unwrap_or(1).saturating_sub(1),
+            )),
+            GoToTarget::Offset(offset) => editor.go_to_offset(offset),
+        }
+    }
+}
+
+#[derive(Props, PartialEq, Clone)]
+pub struct GoToLineProps {
+    editor: Signal<Editor>,
+    on_close: EventHandler<()>,
+}
+
+/// 跳转到行/位置的输入框(Ctrl+G)
+#[component]
+pub fn GoToLine(props: GoToLineProps) -> Element {
+    let mut editor = props.editor;
+    let mut input = use_signal(String::new);
+    let target = GoToTarget::parse(&input());
+    let cursor = editor.with(|e| e.get_cursor_position());
+    let hint = match target {
+        None if !input().is_empty() => "格式：行、行:列 或 :偏移量(:b 字节，:u UTF-16)".to_string(),
+        _ => format!(
+            "当前 {}:{}，共 {} 行",
+            cursor.line + 1,
+            cursor.col + 1,
+            editor.with(|e| e.len_lines())
+        ),
+    };
+
+    let on_keydown = move |e: Event<KeyboardData>| match e.key() {
+        Key::Enter => {
+            let Some(target) = GoToTarget::parse(&input()) else {
+                return;
+            };
+            editor.with_mut(|editorx| target.go_to(editorx));
+            e.prevent_default();
+            props.on_close.call(());
+        }
+        Key::Escape => props.on_close.call(()),
+        _ => {}
+    };
+
+    rsx! {
+        div {
+            style: "position: fixed; top: 48px; left: 50%; transform: translateX(-50%); z-index: 10; background: #f0f0f0; border: 1px solid #ddd; padding: 4px; font-size: 14px;",
+            input {
+                style: "width: 240px;",
+                placeholder: "行[:列] 或 :偏移量",
+                value: "{input}",
+                oninput: move |e| input.set(e.value()),
+                onkeydown: on_keydown,
+                onmounted: move |e| {
+                    spawn(async move {
+                        let _ = e.set_focus(true).await;
+                    });
+                },
+            }
+            div { style: "margin-top: 4px; color: gray;", "{hint}" }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::line_ending::LineEnding;
+
+    #[test]
+    fn parse() {
+        for (input, expected) in [
+            ("12", Some(GoToTarget::Line(12, None))),
+            ("12:5", Some(GoToTarget::Line(12, Some(5)))),
+            (" 3 : 4 ", Some(GoToTarget::Line(3, Some(4)))),
+            (":40", Some(GoToTarget::Offset(Offset::Char(40)))),
+            (": 7", Some(GoToTarget::Offset(Offset::Char(7)))),
+            (":b12", Some(GoToTarget::Offset(Offset::Utf8(12)))),
+            (":u 3", Some(GoToTarget::Offset(Offset::Utf16(3)))),
+            (":x3", None),
+            (":b", None),
+            ("0", Some(GoToTarget::Line(0, None))),
+            ("", None),
+            ("abc", None),
+            ("3:", None),
+            ("3:x", None),
+            ("-1", None),
+            ("1:2:3", None),
+            (":", None),
+            ("99999999999999999999999", None),
+        ] {
+            assert_eq!(GoToTarget::parse(input), expected, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn offsets_in_other_units() {
+        // 中占 3 个字节、1 个 UTF-16 码元；😀 占 4 个字节、2 个 UTF-16 码元
+        for (input, expected) in [
+            (":b3", Position::new(0, 1)),
+            (":b8", Position::new(0, 3)),
+            (":u3", Position::new(0, 2)),
+            (":u5", Position::new(1, 0)),
+        ] {
+            let mut editor = Editor::with_text("中😀a\nb");
+            GoToTarget::parse(input).unwrap().go_to(&mut editor);
+            assert_eq!(editor.get_cursor_position(), expected, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn offsets_follow_the_saved_line_ending() {
+        // 保存后为 "中\r\nab\r\nc"，每个换行多占 1 个单位
+        for (input, expected) in [
+            (":3", Position::new(1, 0)),
+            (":2", Position::new(0, 1)),
+            (":b5", Position::new(1, 0)),
+            (":b7", Position::new(1, 2)),
+            (":b9", Position::new(2, 0)),
+            (":u4", Position::new(1, 1)),
+            (":99", Position::new(2, 1)),
+        ] {
+            let mut editor = Editor::with_text("中\r\nab\r\nc");
+            GoToTarget::parse(input).unwrap().go_to(&mut editor);
+            assert_eq!(editor.get_cursor_position(), expected, "{input:?}");
+        }
+
+        let mut editor = Editor::with_text("中\r\nab\r\nc");
+        editor.set_cursor_position(Position::new(2, 1));
+        assert_eq!(editor.cursor_char_offset(), 8);
+        assert_eq!(editor.cursor_byte_offset(), 10);
+        assert_eq!(editor.cursor_utf16_offset(), 8);
+        editor.set_line_ending(LineEnding::Lf);
+        assert_eq!(editor.cursor_byte_offset(), 8);
+        assert_eq!(editor.position_at(Offset::Utf8(5)), Position::new(1, 1));
+    }
+
+    #[test]
+    fn out_of_range_targets_are_clamped() {
+        for (input, expected) in [
+            ("2", Position::new(1, 0)),
+            ("2:3", Position::new(1, 2)),
+            ("0:0", Position::new(0, 0)),
+            ("99", Position::new(2, 0)),
+            ("1:99", Position::new(0, 3)),
+            (":5", Position::new(1, 1)),
+            (":999", Position::new(2, 2)),
+        ] {
+            let mut editor = Editor::with_text("abc\nde\nfg");
+            GoToTarget::parse(input).unwrap().go_to(&mut editor);
+            assert_eq!(editor.get_cursor_position(), expected, "{input:?}");
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use ropey::{Rope, RopeSlice};
+use unicode_segmentation::{GraphemeCursor, GraphemeIncomplete, UnicodeSegmentation};
+use unicode_width::UnicodeWidthStr;
+
+/// 下一个字素簇边界(字符偏移量)
+pub fn next_grapheme_boundary(rope: &Rope, char_idx: usize) -> usize {
+    if char_idx >= rope.len_chars() {
+        return rope.len_chars();
+    }
+    let slice = rope.slice(..);
+    let byte_idx = next_boundary_byte(&slice, rope.char_to_byte(char_idx));
+    rope.byte_to_char(byte_idx)
+}
+
+/// 上一个字素簇边界(字符偏移量)
+///
+/// unicode-segmentation 反向查找时会把印地语等的辅音连字(GB9c)连同前面的字符合并成一簇，
+/// 反向找到的边界可能偏前，所以再从那里向后查找到最后一个边界
+pub fn prev_grapheme_boundary(rope: &Rope, char_idx: usize) -> usize {
+    if char_idx == 0 {
+        return 0;
+    }
+    let char_idx = char_idx.min(rope.len_chars());
+    let slice = rope.slice(..);
+    let mut boundary = rope.byte_to_char(prev_boundary_byte(&slice, rope.char_to_byte(char_idx)));
+    loop {
+        let next = next_grapheme_boundary(rope, boundary);
+        if next >= char_idx {
+            return boundary;
+        }
+        boundary = next;
+    }
+}
+
+fn prev_boundary_byte(slice: &RopeSlice, byte_idx: usize) -> usize {
+    let (mut chunk, mut chunk_byte_idx, _, _) = slice.chunk_at_byte(byte_idx);
+    let mut cursor = GraphemeCursor::new(byte_idx, slice.len_bytes(), true);
+    loop {
+        match cursor.prev_boundary(chunk, chunk_byte_idx) {
+            Ok(None) => return 0,
+            Ok(Some(n)) => return n,
+            Err(GraphemeIncomplete::PrevChunk) => {
+                let (prev, prev_byte_idx, _, _) = slice.chunk_at_byte(chunk_byte_idx - 1);
+                chunk = prev;
+                chunk_byte_idx = prev_byte_idx;
+            }
+            Err(GraphemeIncomplete::PreContext(n)) => {
+                let (context, context_byte_idx, _, _) = slice.chunk_at_byte(n - 1);
+                cursor.provide_context(&context[..n - context_byte_idx], context_byte_idx);
+            }
+            Err(_) => unreachable!(),
+        }
+    }
+}
+
+fn next_boundary_byte(slice: &RopeSlice, byte_idx: usize) -> usize {
+    let (mut chunk, mut chunk_byte_idx, _, _) = slice.chunk_at_byte(byte_idx);
+    let mut cursor = GraphemeCursor::new(byte_idx, slice.len_bytes(), true);
+    loop {
+        match cursor.next_boundary(chunk, chunk_byte_idx) {
+            Ok(None) => return slice.len_bytes(),
+            Ok(Some(n)) => return n,
+            Err(GraphemeIncomplete::NextChunk) => {
+                chunk_byte_idx += chunk.len();
+                chunk = slice.chunk_at_byte(chunk_byte_idx).0;
+            }
+            Err(GraphemeIncomplete::PreContext(n)) => {
+                // 只提供 n 之前的内容，n 可能位于块的中间
+                let (context, context_byte_idx, _, _) = slice.chunk_at_byte(n - 1);
+                cursor.provide_context(&context[..n - context_byte_idx], context_byte_idx);
+            }
+            Err(_) => unreachable!(),
+        }
+    }
+}
+
+/// 把一行文本拆成字素簇，返回 (起始列, 字素簇)
+pub fn line_graphemes(line: &str) -> impl Iterator<Item = (usize, &str)> {
+    line.graphemes(true).scan(0, |col, grapheme| {
+        let start = *col;
+        *col += grapheme.chars().count();
+        Some((start, grapheme))
+    })
+}
+
+/// 字素簇的显示宽度：按东亚宽度，中日韩文字和 emoji 占两列，其余占一列；
+/// 非空的簇至少占一列
+pub fn grapheme_width(grapheme: &str) -> usize {
+    match grapheme {
+        "" => 0,
+        _ => grapheme.width().max(1),
+    }
+}
+
+/// 显示列 column 处放置字素簇后的显示列，制表符对齐到下一个制表位
+pub fn advance_column(column: usize, grapheme: &str, tab_width: usize) -> usize {
+    if grapheme == "\t" {
+        (column / tab_width + 1) * tab_width
+    } else {
+        column + grapheme_width(grapheme)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 从头到尾依次取下一个边界
+    fn forward(text: &str) -> Vec<usize> {
+        let rope = Rope::from_str(text);
+        let mut boundaries = vec![0];
+        while *boundaries.last().unwrap() < rope.len_chars() {
+            let idx = *boundaries.last().unwrap();
+            boundaries.push(next_grapheme_boundary(&rope, idx));
+        }
+        boundaries
+    }
+
+    /// 从尾到头依次取上一个边界
+    fn backward(text: &str) -> Vec<usize> {
+        let rope = Rope::from_str(text);
+        let mut boundaries = vec![rope.len_chars()];
+        while *boundaries.last().unwrap() > 0 {
+            let idx = *boundaries.last().unwrap();
+            boundaries.push(prev_grapheme_boundary(&rope, idx));
+        }
+        boundaries.reverse();
+        boundaries
+    }
+
+    #[test]
+    fn zwj_emoji_is_one_cluster() {
+        // 👨‍👩‍👧：三个人物由两个 ZWJ 连接
+        let text = "a\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}b";
+        assert_eq!(forward(text), [0, 1, 6, 7]);
+        assert_eq!(backward(text), [0, 1, 6, 7]);
+    }
+
+    #[test]
+    fn flags_pair_regional_indicators() {
+        // 🇨🇳🇺🇸：两面旗帜，各由两个区域指示符组成
+        let text = "\u{1F1E8}\u{1F1F3}\u{1F1FA}\u{1F1F8}";
+        assert_eq!(forward(text), [0, 2, 4]);
+        assert_eq!(backward(text), [0, 2, 4]);
+    }
+
+    #[test]
+    fn indic_clusters() {
+        // न म स्ते
+        assert_eq!(forward("नमस्ते"), [0, 1, 2, 6]);
+        assert_eq!(backward("नमस्ते"), [0, 1, 2, 6]);
+        let rope = Rope::from_str("hello world नमस्ते");
+        assert_eq!(prev_grapheme_boundary(&rope, 18), 14);
+    }
+
+    #[test]
+    fn line_breaks() {
+        assert_eq!(forward("a\r\nb\n"), [0, 1, 3, 4, 5]);
+        assert_eq!(backward("a\r\nb\n"), [0, 1, 3, 4, 5]);
+    }
+
+    #[test]
+    fn clusters_across_chunks() {
+        // 足够长的文本会被 Rope 分成多个块
+        let prefix = format!("{}\n", "x".repeat(15)).repeat(250);
+        let text = format!("{}नमस्ते\u{1F1E8}\u{1F1F3}\u{1F1FA}\u{1F1F8}", prefix);
+        let rope = Rope::from_str(&text);
+        assert!(rope.chunks().count() > 1);
+        assert_eq!(forward(&text)[4000..], [4000, 4001, 4002, 4006, 4008, 4010]);
+        assert_eq!(
+            backward(&text)[4000..],
+            [4000, 4001, 4002, 4006, 4008, 4010]
+        );
+    }
+
+    #[test]
+    fn long_lines_step_back_locally() {
+        // 压缩后的单行文本：每一步只看光标附近，不从行首重新扫描
+        let text = format!("{}नमस्ते", "x".repeat(50_000));
+        let boundaries = backward(&text);
+        assert_eq!(boundaries.len(), 50_004);
+        assert_eq!(boundaries[50_000..], [50_000, 50_001, 50_002, 50_006]);
+    }
+
+    #[test]
+    fn widths() {
+        for (grapheme, expected) in [
+            ("", 0),
+            ("a", 1),
+            ("é", 1),
+            ("e\u{301}", 1),
+            ("ж", 1),
+            ("λ", 1),
+            ("中", 2),
+            ("あ", 2),
+            ("😀", 2),
+            ("\u{200b}", 1),
+        ] {
+            assert_eq!(grapheme_width(grapheme), expected, "{grapheme:?}");
+        }
+        assert_eq!(advance_column(3, "\t", 4), 4);
+        assert_eq!(advance_column(3, "ü", 4), 4);
+    }
+}
@@ -0,0 +1,299 @@
+use super::selection::Selection;
+
+const HISTORY_LIMIT: usize = 1000;
+
+/// 单次文本修改(以字符偏移量记录)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edit {
+    pub offset: usize,
+    pub removed: String,
+    pub inserted: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EditKind {
+    Typing,
+    Other,
+}
+
+/// 一次可撤销的操作，包含若干修改以及前后的选区
+#[derive(Debug, Clone)]
+pub struct Transaction {
+    pub kind: EditKind,
+    pub edits: Vec<Edit>,
+    pub cursor_before: Vec<Selection>,
+    pub cursor_after: Vec<Selection>,
+}
+
+impl Transaction {
+    /// 连续输入的字符合并为以单词为单位的操作(多光标时逐个光标比较)
+    fn can_merge(&self, next: &Transaction) -> bool {
+        if self.kind != EditKind::Typing || next.kind != EditKind::Typing {
+            return false;
+        }
+        if self.edits.len() != next.edits.len() {
+            return false;
+        }
+        // next 中第 i 处修改的偏移量包含了它之前各处新插入的字符
+        let mut shift = 0;
+        for (last, edit) in self.edits.iter().zip(next.edits.iter()) {
+            if !last.removed.is_empty() || !edit.removed.is_empty() {
+                return false;
+            }
+            if edit.offset != last.offset + last.inserted.chars().count() + shift {
+                return false;
+            }
+            // 空白之后开始输入新单词时断开
+            let ends_with_space = last.inserted.ends_with(char::is_whitespace);
+            let starts_with_space = edit.inserted.starts_with(char::is_whitespace);
+            if ends_with_space && !starts_with_space {
+                return false;
+            }
+            shift += edit.inserted.chars().count();
+        }
+        true
+    }
+
+    fn merge(&mut self, next: Transaction) {
+        let mut shift = 0;
+        for (last, edit) in self.edits.iter_mut().zip(next.edits) {
+            last.offset += shift;
+            shift += edit.inserted.chars().count();
+            last.inserted.push_str(&edit.inserted);
+        }
+        self.cursor_after = next.cursor_after;
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    undo_stack: Vec<Transaction>,
+    redo_stack: Vec<Transaction>,
+    /// 最近一次操作之后移动过光标，下一次输入不再与它合并
+    sealed: bool,
+    /// begin_group 的嵌套层数，大于 0 时的操作合并为一次
+    group_depth: usize,
+    /// 当前组的第一次操作已压入撤销栈
+    group_started: bool,
+    /// 与文件内容一致时撤销栈的深度
+    saved_depth: usize,
+    /// 与文件内容一致的状态已无法通过撤销或重做回到
+    saved_lost: bool,
+}
+
+impl History {
+    /// 记录一次操作，并清空重做栈
+    pub fn push(&mut self, transaction: Transaction) {
+        if transaction.edits.is_empty() {
+            return;
+        }
+        self.redo_stack.clear();
+        let sealed = std::mem::take(&mut self.sealed);
+        // 保存时的状态在被清空的重做栈中，或是下面被合并修改的最后一次操作
+        let depth = self.undo_stack.len();
+        self.saved_lost |= self.saved_depth > depth;
+        if let Some(last) = self.undo_stack.last_mut() {
+            if self.group_started {
+                self.saved_lost |= self.saved_depth == depth;
+                last.kind = EditKind::Other;
+                last.edits.extend(transaction.edits);
+                last.cursor_after = transaction.cursor_after;
+                return;
+            }
+            if self.group_depth == 0 && !sealed && last.can_merge(&transaction) {
+                self.saved_lost |= self.saved_depth == depth;
+                last.merge(transaction);
+                return;
+            }
+        }
+        self.group_started = self.group_depth > 0;
+        self.undo_stack.push(transaction);
+        if self.undo_stack.len() > HISTORY_LIMIT {
+            self.undo_stack.remove(0);
+            self.saved_lost |= self.saved_depth == 0;
+            self.saved_depth = self.saved_depth.saturating_sub(1);
+        }
+    }
+
+    /// 保存文件后调用，记下与文件一致的状态
+    pub fn mark_saved(&mut self) {
+        self.saved_depth = self.undo_stack.len();
+        self.saved_lost = false;
+        self.sealed = true;
+    }
+
+    /// 文本之外影响文件内容的修改(如换行符)，撤销后也不再与文件一致
+    pub fn mark_modified(&mut self) {
+        self.saved_lost = true;
+    }
+
+    /// 文本是否与最近一次打开或保存的文件一致
+    pub fn is_clean(&self) -> bool {
+        !self.saved_lost && self.saved_depth == self.undo_stack.len()
+    }
+
+    /// 光标移动后调用，断开连续输入的合并
+    pub fn seal(&mut self) {
+        self.sealed = true;
+    }
+
+    /// 开始一组操作，到对应的 end_group 为止的修改作为一次撤销
+    pub fn begin_group(&mut self) {
+        self.group_depth += 1;
+    }
+
+    pub fn end_group(&mut self) {
+        self.group_depth = self.group_depth.saturating_sub(1);
+        if self.group_depth == 0 {
+            self.group_started = false;
+            self.sealed = true;
+        }
+    }
+
+    /// 取出最近一次操作，移入重做栈
+    pub fn undo(&mut self) -> Option<&Transaction> {
+        self.group_started = false;
+        let transaction = self.undo_stack.pop()?;
+        self.redo_stack.push(transaction);
+        self.redo_stack.last()
+    }
+
+    /// 取出最近一次撤销的操作，移回撤销栈
+    pub fn redo(&mut self) -> Option<&Transaction> {
+        self.group_started = false;
+        let transaction = self.redo_stack.pop()?;
+        self.undo_stack.push(transaction);
+        self.undo_stack.last()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::editor_new::Editor;
+    use crate::components::position::Position;
+
+    fn typing(edits: &[(usize, &str)]) -> Transaction {
+        Transaction {
+            kind: EditKind::Typing,
+            edits: edits
+                .iter()
+                .map(|&(offset, inserted)| Edit {
+                    offset,
+                    removed: String::new(),
+                    inserted: inserted.to_string(),
+                })
+                .collect(),
+            cursor_before: Vec::new(),
+            cursor_after: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn merge_shifts_later_cursors() {
+        // "ab\ncd" 两个光标在行首，先输入 x 再输入 y
+        let mut history = History::default();
+        history.push(typing(&[(0, "x"), (4, "x")]));
+        history.push(typing(&[(1, "y"), (6, "y")]));
+        assert_eq!(history.undo_stack.len(), 1);
+        let edits = &history.undo_stack[0].edits;
+        assert_eq!((edits[0].offset, edits[0].inserted.as_str()), (0, "xy"));
+        assert_eq!((edits[1].offset, edits[1].inserted.as_str()), (5, "xy"));
+    }
+
+    #[test]
+    fn non_contiguous_typing_is_not_merged() {
+        let mut history = History::default();
+        history.push(typing(&[(0, "x"), (4, "x")]));
+        history.push(typing(&[(1, "y"), (5, "y")]));
+        assert_eq!(history.undo_stack.len(), 2);
+    }
+
+    #[test]
+    fn undo_coalesced_typing_from_two_cursors() {
+        let mut editor = Editor::with_text("ab\ncd");
+        editor.add_cursor(Position::new(1, 0));
+        for ch in "xy".chars() {
+            editor.type_char(ch);
+        }
+        assert_eq!(editor.get_text(), "xyab\nxycd");
+        editor.undo();
+        assert_eq!(editor.get_text(), "ab\ncd");
+        let heads: Vec<Position> = editor.get_selections().iter().map(|s| s.head).collect();
+        assert_eq!(heads, [Position::new(0, 0), Position::new(1, 0)]);
+        editor.redo();
+        assert_eq!(editor.get_text(), "xyab\nxycd");
+    }
+
+    #[test]
+    fn group_is_one_undo_step() {
+        let mut editor = Editor::with_text("one two");
+        editor.type_char('x');
+        editor.begin_undo_group();
+        editor.set_cursor_position(Position::new(0, 5));
+        editor.select_to(Position::new(0, 8));
+        editor.cut_text();
+        editor.type_char('a');
+        editor.type_char('b');
+        editor.end_undo_group();
+        editor.type_char('c');
+        assert_eq!(editor.get_text(), "xone abc");
+        editor.undo();
+        assert_eq!(editor.get_text(), "xone ab");
+        editor.undo();
+        assert_eq!(editor.get_text(), "xone two");
+        assert_eq!(editor.get_cursor_position(), Position::new(0, 8));
+        editor.redo();
+        assert_eq!(editor.get_text(), "xone ab");
+    }
+
+    #[test]
+    fn cursor_move_breaks_coalescing() {
+        let mut editor = Editor::with_text("");
+        editor.type_char('a');
+        editor.type_char('b');
+        editor.move_cursor_left(false);
+        editor.move_cursor_right(false);
+        editor.type_char('c');
+        assert_eq!(editor.get_text(), "abc");
+        editor.undo();
+        assert_eq!(editor.get_text(), "ab");
+        editor.undo();
+        assert_eq!(editor.get_text(), "");
+    }
+
+    #[test]
+    fn clean_state_follows_undo_and_redo() {
+        let mut history = History::default();
+        assert!(history.is_clean());
+        history.push(typing(&[(0, "a")]));
+        assert!(!history.is_clean());
+        history.mark_saved();
+        assert!(history.is_clean());
+        // 保存后的输入不与保存前的合并，撤销即回到保存时的状态
+        history.push(typing(&[(1, "b")]));
+        assert!(!history.is_clean());
+        history.undo();
+        assert!(history.is_clean());
+        history.undo();
+        assert!(!history.is_clean());
+        history.redo();
+        assert!(history.is_clean());
+        // 撤销到保存之前再修改后无法回到保存时的状态
+        history.undo();
+        history.push(typing(&[(0, "c")]));
+        history.undo();
+        assert!(!history.is_clean());
+        history.redo();
+        assert!(!history.is_clean());
+    }
+
+    #[test]
+    fn other_modifications_are_never_clean() {
+        let mut history = History::default();
+        history.mark_modified();
+        assert!(!history.is_clean());
+        history.mark_saved();
+        assert!(history.is_clean());
+    }
+}
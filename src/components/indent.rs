@@ -0,0 +1,135 @@
+/// 缩进设置：宽度为 width 的空格，或硬制表符(显示宽度为 width)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IndentConfig {
+    pub width: usize,
+    pub hard_tabs: bool,
+}
+
+impl Default for IndentConfig {
+    fn default() -> Self {
+        IndentConfig {
+            width: 4,
+            hard_tabs: false,
+        }
+    }
+}
+
+impl IndentConfig {
+    /// 工具栏中可选的缩进设置
+    pub const PRESETS: [IndentConfig; 5] = [
+        IndentConfig::spaces(2),
+        IndentConfig::spaces(4),
+        IndentConfig::spaces(8),
+        IndentConfig::tabs(4),
+        IndentConfig::tabs(8),
+    ];
+
+    const fn spaces(width: usize) -> Self {
+        IndentConfig {
+            width,
+            hard_tabs: false,
+        }
+    }
+
+    const fn tabs(width: usize) -> Self {
+        IndentConfig {
+            width,
+            hard_tabs: true,
+        }
+    }
+
+    /// 工具栏中显示的名称，如 `空格: 4`
+    pub fn label(&self) -> String {
+        let kind = if self.hard_tabs {
+            "制表符"
+        } else {
+            "空格"
+        };
+        format!("{}: {}", kind, self.width)
+    }
+
+    /// 一级缩进的文本
+    pub fn unit(&self) -> String {
+        if self.hard_tabs {
+            "\t".to_string()
+        } else {
+            " ".repeat(self.width)
+        }
+    }
+
+    /// 在显示列 column 处按 Tab 插入的文本(软缩进时补齐到下一个制表位)
+    pub fn unit_at(&self, column: usize) -> String {
+        if self.hard_tabs {
+            "\t".to_string()
+        } else {
+            " ".repeat(self.width - column % self.width)
+        }
+    }
+
+    /// 减少一级缩进时，行首需要删除的字符数
+    pub fn dedent_len(&self, line: &str) -> usize {
+        if line.starts_with('\t') {
+            return 1;
+        }
+        line.chars()
+            .take(self.width)
+            .take_while(|c| *c == ' ')
+            .count()
+    }
+
+    /// 工具栏选项的值，如 `spaces-4`、`tabs-4`
+    pub fn to_option(self) -> String {
+        let kind = if self.hard_tabs { "tabs" } else { "spaces" };
+        format!("{}-{}", kind, self.width)
+    }
+
+    pub fn from_option(value: &str) -> Option<Self> {
+        let (kind, width) = value.split_once('-')?;
+        let width = width.parse().ok().filter(|w| *w > 0)?;
+        Some(IndentConfig {
+            width,
+            hard_tabs: kind == "tabs",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_at_pads_to_tab_stop() {
+        let spaces = IndentConfig::spaces(4);
+        for (column, expected) in [(0, 4), (1, 3), (3, 1), (4, 4), (6, 2)] {
+            assert_eq!(spaces.unit_at(column), " ".repeat(expected), "{column}");
+        }
+        assert_eq!(IndentConfig::tabs(4).unit_at(3), "\t");
+        assert_eq!(IndentConfig::tabs(8).unit(), "\t");
+    }
+
+    #[test]
+    fn dedent_len() {
+        let spaces = IndentConfig::spaces(4);
+        for (line, expected) in [
+            ("a", 0),
+            ("  a", 2),
+            ("    a", 4),
+            ("      a", 4),
+            ("\t\ta", 1),
+            (" \ta", 1),
+            ("", 0),
+        ] {
+            assert_eq!(spaces.dedent_len(line), expected, "{line:?}");
+        }
+        assert_eq!(IndentConfig::tabs(4).dedent_len("  a"), 2);
+    }
+
+    #[test]
+    fn options() {
+        for config in IndentConfig::PRESETS {
+            assert_eq!(IndentConfig::from_option(&config.to_option()), Some(config));
+        }
+        assert_eq!(IndentConfig::from_option("spaces-0"), None);
+        assert_eq!(IndentConfig::from_option("tabs"), None);
+    }
+}
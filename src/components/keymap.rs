@@ -0,0 +1,697 @@
+use super::command::{EditorCommand, Motion};
+use super::config::config_dir;
+use super::emacs;
+use dioxus::prelude::*;
+use fxhash::FxHashMap;
+use std::fmt;
+
+/// 按键方案
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum KeymapProfile {
+    #[default]
+    Default,
+    /// 在默认绑定之上叠加 Vim 的模式编辑
+    Vim,
+    /// Emacs 风格的光标移动和剪切环
+    Emacs,
+}
+
+impl KeymapProfile {
+    pub const ALL: [KeymapProfile; 3] = [
+        KeymapProfile::Default,
+        KeymapProfile::Vim,
+        KeymapProfile::Emacs,
+    ];
+
+    /// 工具栏中显示的名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            KeymapProfile::Default => "默认按键",
+            KeymapProfile::Vim => "Vim",
+            KeymapProfile::Emacs => "Emacs",
+        }
+    }
+
+    /// 工具栏选项的值
+    pub fn to_option(self) -> &'static str {
+        match self {
+            KeymapProfile::Default => "default",
+            KeymapProfile::Vim => "vim",
+            KeymapProfile::Emacs => "emacs",
+        }
+    }
+
+    pub fn from_option(value: &str) -> Option<Self> {
+        match value {
+            "default" => Some(KeymapProfile::Default),
+            "vim" => Some(KeymapProfile::Vim),
+            "emacs" => Some(KeymapProfile::Emacs),
+            _ => None,
+        }
+    }
+
+    /// 该方案先处理、不会交给默认绑定的按键(Vim 按普通模式计算)
+    pub fn intercepts(&self, chord: &Chord) -> bool {
+        let key = chord.key.as_str();
+        match self {
+            KeymapProfile::Default => false,
+            // 普通模式下只有 Ctrl+R 之外的 Ctrl/Meta 组合键和翻页键交给默认绑定
+            KeymapProfile::Vim if chord.ctrl || chord.meta => key == "r" && !chord.shift,
+            KeymapProfile::Vim => !matches!(key, "pageup" | "pagedown"),
+            KeymapProfile::Emacs => {
+                let key = if key == "space" { " " } else { key };
+                let letter = key.chars().all(char::is_alphabetic);
+                if key.chars().count() != 1 || (chord.ctrl && chord.shift && letter) {
+                    return false;
+                }
+                (chord.ctrl && emacs::CTRL_KEYS.contains(&key))
+                    || (chord.alt && emacs::ALT_KEYS.contains(&key))
+            }
+        }
+    }
+}
+
+/// 需要界面参与的操作，和编辑命令一样可以绑定到按键
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyAction {
+    Command(EditorCommand),
+    Find,
+    Replace,
+    GoToLine,
+    /// 从系统剪贴板粘贴
+    PasteClipboard,
+    ToggleMacroRecording,
+    MacroPanel,
+    ReloadKeymap,
+    CommandPalette,
+    ToggleWhitespace,
+}
+
+/// 界面操作的名称，新增的操作在这里登记后即可绑定按键并出现在命令面板中
+pub const UI_ACTIONS: &[(&str, KeyAction)] = &[
+    ("find", KeyAction::Find),
+    ("replace", KeyAction::Replace),
+    ("go_to_line", KeyAction::GoToLine),
+    ("paste", KeyAction::PasteClipboard),
+    ("toggle_macro_recording", KeyAction::ToggleMacroRecording),
+    ("macro_panel", KeyAction::MacroPanel),
+    ("reload_keymap", KeyAction::ReloadKeymap),
+    ("command_palette", KeyAction::CommandPalette),
+    ("toggle_whitespace", KeyAction::ToggleWhitespace),
+];
+
+impl KeyAction {
+    /// 按名称解析：界面操作的名称，或 `EditorCommand` 的文本形式
+    pub fn parse(name: &str) -> Result<Self, String> {
+        let name = name.trim();
+        if let Some((_, action)) = UI_ACTIONS.iter().find(|(n, _)| *n == name) {
+            return Ok(action.clone());
+        }
+        name.parse()
+            .map(KeyAction::Command)
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl KeyAction {
+    /// 命令面板中显示的名称
+    pub fn title(&self) -> &'static str {
+        match self {
+            KeyAction::Command(command) => command.title(),
+            KeyAction::Find => "查找",
+            KeyAction::Replace => "替换",
+            KeyAction::GoToLine => "转到行",
+            KeyAction::PasteClipboard => "粘贴",
+            KeyAction::ToggleMacroRecording => "开始/停止录制宏",
+            KeyAction::MacroPanel => "回放宏",
+            KeyAction::ReloadKeymap => "重新加载按键配置",
+            KeyAction::CommandPalette => "命令面板",
+            KeyAction::ToggleWhitespace => "显示/隐藏空白字符",
+        }
+    }
+}
+
+impl fmt::Display for KeyAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeyAction::Command(command) => write!(f, "{}", command),
+            _ => {
+                let name = UI_ACTIONS.iter().find(|(_, a)| a == self).map(|(n, _)| *n);
+                write!(f, "{}", name.unwrap_or_default())
+            }
+        }
+    }
+}
+
+/// 一次按键：修饰键加上按键名称(字符键为小写字符)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Chord {
+    ctrl: bool,
+    alt: bool,
+    shift: bool,
+    meta: bool,
+    key: String,
+}
+
+impl Chord {
+    /// 由按键事件得到，单独按下修饰键时返回 None
+    pub fn from_key(key: &Key, modifiers: Modifiers) -> Option<Self> {
+        let key = match key {
+            Key::Control | Key::Shift | Key::Alt | Key::Meta => return None,
+            Key::Character(c) if c == " " => "space".to_string(),
+            Key::Character(c) => c.to_lowercase(),
+            Key::ArrowUp => "up".to_string(),
+            Key::ArrowDown => "down".to_string(),
+            Key::ArrowLeft => "left".to_string(),
+            Key::ArrowRight => "right".to_string(),
+            key => key.to_string().to_lowercase(),
+        };
+        Some(Chord {
+            ctrl: modifiers.ctrl(),
+            alt: modifiers.alt(),
+            shift: modifiers.shift(),
+            meta: modifiers.meta(),
+            key,
+        })
+    }
+
+    /// 解析 `ctrl+shift+z` 形式的按键，修饰键顺序任意
+    fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim().to_lowercase();
+        let (modifiers, key) = match text.strip_suffix("++") {
+            Some(modifiers) => (modifiers, "+"),
+            None => text.rsplit_once('+').unwrap_or(("", &text)),
+        };
+        let mut chord = Chord {
+            ctrl: false,
+            alt: false,
+            shift: false,
+            meta: false,
+            key: match key {
+                "" => return Err(format!("缺少按键：{}", text)),
+                "esc" => "escape",
+                "return" => "enter",
+                "del" => "delete",
+                "arrowup" => "up",
+                "arrowdown" => "down",
+                "arrowleft" => "left",
+                "arrowright" => "right",
+                key => key,
+            }
+            .to_string(),
+        };
+        for modifier in modifiers.split('+').filter(|m| !m.is_empty()) {
+            match modifier {
+                "ctrl" | "control" => chord.ctrl = true,
+                "alt" | "option" => chord.alt = true,
+                "shift" => chord.shift = true,
+                "meta" | "cmd" | "super" => chord.meta = true,
+                _ => return Err(format!("未知的修饰键：{}", modifier)),
+            }
+        }
+        Ok(chord)
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (on, name) in [
+            (self.ctrl, "ctrl+"),
+            (self.alt, "alt+"),
+            (self.shift, "shift+"),
+            (self.meta, "meta+"),
+        ] {
+            if on {
+                write!(f, "{}", name)?;
+            }
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+/// 解析以空格分隔的按键序列，如 `ctrl+k ctrl+c`
+fn parse_sequence(text: &str) -> Result<Vec<Chord>, String> {
+    let sequence = text
+        .split_whitespace()
+        .map(Chord::parse)
+        .collect::<Result<Vec<_>, _>>()?;
+    if sequence.is_empty() {
+        return Err("按键序列为空".to_string());
+    }
+    Ok(sequence)
+}
+
+fn format_sequence(sequence: &[Chord]) -> String {
+    sequence
+        .iter()
+        .map(|chord| chord.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 默认按键绑定，`page_up`/`page_down` 不带参数时翻一页
+const DEFAULT_BINDINGS: &[(&str, &str)] = &[
+    ("ctrl+f", "find"),
+    ("ctrl+h", "replace"),
+    ("ctrl+g", "go_to_line"),
+    ("ctrl+v", "paste"),
+    ("ctrl+shift+r", "toggle_macro_recording"),
+    ("ctrl+shift+p", "command_palette"),
+    ("ctrl+shift+m", "macro_panel"),
+    ("ctrl+k ctrl+r", "reload_keymap"),
+    ("ctrl+z", "undo"),
+    ("ctrl+shift+z", "redo"),
+    ("ctrl+y", "redo"),
+    ("ctrl+c", "copy"),
+    ("ctrl+x", "cut"),
+    ("ctrl+a", "select_all"),
+    ("ctrl+shift+d", "duplicate_lines"),
+    ("ctrl+shift+k", "delete_lines"),
+    ("ctrl+shift+\\", "jump_to_matching_bracket"),
+    ("ctrl+shift+|", "jump_to_matching_bracket"),
+    ("ctrl+shift+[", "fold"),
+    ("ctrl+shift+{", "fold"),
+    ("ctrl+shift+]", "unfold"),
+    ("ctrl+shift+}", "unfold"),
+    ("ctrl+j", "join_lines"),
+    ("ctrl+d", "add_next_occurrence"),
+    ("ctrl+/", "toggle_line_comment"),
+    ("ctrl+shift+/", "toggle_block_comment"),
+    ("ctrl+shift+?", "toggle_block_comment"),
+    ("alt+up", "move_lines_up"),
+    ("alt+down", "move_lines_down"),
+    ("ctrl+alt+up", "add_cursor_above"),
+    ("ctrl+alt+down", "add_cursor_below"),
+    ("escape", "clear_secondary_cursors"),
+    ("home", "move line_start"),
+    ("shift+home", "select line_start"),
+    ("end", "move line_end"),
+    ("shift+end", "select line_end"),
+    ("ctrl+home", "move document_start"),
+    ("ctrl+shift+home", "select document_start"),
+    ("ctrl+end", "move document_end"),
+    ("ctrl+shift+end", "select document_end"),
+    ("pageup", "move page_up"),
+    ("shift+pageup", "select page_up"),
+    ("pagedown", "move page_down"),
+    ("shift+pagedown", "select page_down"),
+    ("left", "move left"),
+    ("shift+left", "select left"),
+    ("right", "move right"),
+    ("shift+right", "select right"),
+    ("up", "move up"),
+    ("shift+up", "select up"),
+    ("down", "move down"),
+    ("shift+down", "select down"),
+    ("ctrl+left", "move word_left"),
+    ("ctrl+shift+left", "select word_left"),
+    ("ctrl+right", "move word_right"),
+    ("ctrl+shift+right", "select word_right"),
+    ("enter", "newline"),
+    ("shift+enter", "newline"),
+    ("backspace", "backspace"),
+    ("shift+backspace", "backspace"),
+    ("ctrl+backspace", "delete_word_backward"),
+    ("delete", "delete"),
+    ("ctrl+delete", "delete_word_forward"),
+    ("tab", "indent"),
+    ("shift+tab", "dedent"),
+];
+
+/// 按键的处理结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyPress {
+    Action(KeyAction),
+    /// 按键序列尚未完成
+    Pending,
+    /// 按键序列的后续按键没有绑定，整个序列作废
+    Cancelled,
+    Unbound,
+}
+
+/// 按键绑定表：默认绑定加上用户配置文件 `~/.config/editor-x/keymap.toml`
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: FxHashMap<Vec<Chord>, KeyAction>,
+    /// 已按下的序列前缀
+    pending: Vec<Chord>,
+    /// 加载配置时的错误和冲突
+    diagnostics: Vec<String>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = DEFAULT_BINDINGS
+            .iter()
+            .map(|(keys, action)| {
+                let sequence = parse_sequence(keys).expect("默认按键绑定有误");
+                (
+                    sequence,
+                    KeyAction::parse(action).expect("默认按键绑定有误"),
+                )
+            })
+            .collect();
+        Keymap {
+            bindings,
+            pending: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+}
+
+impl Keymap {
+    /// 读取用户配置；文件不存在时使用默认绑定，文件无效时使用默认绑定并记录错误
+    pub fn load() -> Self {
+        let Some(path) = config_dir().map(|dir| dir.join("keymap.toml")) else {
+            return Keymap::default();
+        };
+        let Ok(text) = std::fs::read_to_string(&path) else {
+            return Keymap::default();
+        };
+        Keymap::from_toml(&text).unwrap_or_else(|err| {
+            let mut keymap = Keymap::default();
+            keymap
+                .diagnostics
+                .push(format!("{} 无效，已使用默认按键：{}", path.display(), err));
+            keymap
+        })
+    }
+
+    /// 在默认绑定之上应用配置：
+    ///
+    /// ```toml
+    /// [bindings]
+    /// "ctrl+k ctrl+c" = "toggle_line_comment"
+    /// "ctrl+d" = ""   # 空字符串解除绑定
+    /// ```
+    ///
+    /// 一个序列是另一个序列的前缀时后者无法触发，视为冲突：
+    /// 与默认绑定冲突时去掉默认绑定，配置之间冲突时保留较短的序列
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let table: toml::Table = text
+            .parse()
+            .map_err(|e: toml::de::Error| e.message().to_string())?;
+        let mut keymap = Keymap::default();
+        let Some(bindings) = table.get("bindings") else {
+            return Ok(keymap);
+        };
+        let bindings = bindings
+            .as_table()
+            .ok_or_else(|| "bindings 必须是表".to_string())?;
+
+        let mut user: Vec<(Vec<Chord>, Option<KeyAction>)> = Vec::new();
+        for (keys, action) in bindings {
+            let sequence = parse_sequence(keys)?;
+            let action = action
+                .as_str()
+                .ok_or_else(|| format!("{} 的值必须是命令名称", keys))?;
+            let action = match action.trim() {
+                "" => None,
+                action => Some(KeyAction::parse(action)?),
+            };
+            if let Some((_, previous)) = user.iter().find(|(s, _)| *s == sequence) {
+                if *previous != action {
+                    keymap.diagnostics.push(format!(
+                        "{} 被重复绑定，已忽略 {}",
+                        format_sequence(&sequence),
+                        keys
+                    ));
+                }
+                continue;
+            }
+            user.push((sequence, action));
+        }
+        // 较短的序列优先
+        user.sort_by_key(|(sequence, _)| sequence.len());
+
+        let mut accepted: Vec<Vec<Chord>> = Vec::new();
+        for (sequence, action) in user {
+            keymap.bindings.remove(&sequence);
+            let Some(action) = action else {
+                continue;
+            };
+            if let Some(prefix) = accepted.iter().find(|s| sequence.starts_with(s)) {
+                keymap.diagnostics.push(format!(
+                    "{} 与 {} 冲突，已忽略前者",
+                    format_sequence(&sequence),
+                    format_sequence(prefix)
+                ));
+                continue;
+            }
+            let conflicts: Vec<Vec<Chord>> = keymap
+                .bindings
+                .keys()
+                .filter(|s| s.starts_with(&sequence) || sequence.starts_with(s))
+                .cloned()
+                .collect();
+            for conflict in conflicts {
+                keymap.diagnostics.push(format!(
+                    "{} 覆盖了默认绑定 {}",
+                    format_sequence(&sequence),
+                    format_sequence(&conflict)
+                ));
+                keymap.bindings.remove(&conflict);
+            }
+            keymap.bindings.insert(sequence.clone(), action);
+            accepted.push(sequence);
+        }
+        Ok(keymap)
+    }
+
+    /// 处理一次按键
+    pub fn press(&mut self, chord: Chord) -> KeyPress {
+        let had_prefix = !self.pending.is_empty();
+        let mut sequence = std::mem::take(&mut self.pending);
+        sequence.push(chord);
+        if let Some(action) = self.bindings.get(&sequence) {
+            return KeyPress::Action(action.clone());
+        }
+        if self
+            .bindings
+            .keys()
+            .any(|s| s.len() > sequence.len() && s.starts_with(&sequence))
+        {
+            self.pending = sequence;
+            return KeyPress::Pending;
+        }
+        if had_prefix {
+            KeyPress::Cancelled
+        } else {
+            KeyPress::Unbound
+        }
+    }
+
+    /// 在给定按键方案下能触发某个动作的按键序列(有多个时取最短的)
+    pub fn binding_for(&self, action: &KeyAction, profile: KeymapProfile) -> Option<String> {
+        self.bindings
+            .iter()
+            .filter(|(sequence, a)| {
+                *a == action && !sequence.iter().any(|chord| profile.intercepts(chord))
+            })
+            .map(|(sequence, _)| format_sequence(sequence))
+            .min_by_key(|keys| (keys.split(' ').count(), keys.len(), keys.clone()))
+    }
+
+    /// 状态栏文本：未完成的按键序列，或加载配置时的问题
+    pub fn status(&self) -> String {
+        if !self.pending.is_empty() {
+            return format!("{} …", format_sequence(&self.pending));
+        }
+        self.diagnostics.join("；")
+    }
+}
+
+/// 把不带行数的翻页命令换算为一页的行数
+pub fn with_page_lines(command: EditorCommand, page_lines: usize) -> EditorCommand {
+    match command {
+        EditorCommand::Move {
+            motion: Motion::PageUp(0),
+            select,
+        } => EditorCommand::Move {
+            motion: Motion::PageUp(page_lines),
+            select,
+        },
+        EditorCommand::Move {
+            motion: Motion::PageDown(0),
+            select,
+        } => EditorCommand::Move {
+            motion: Motion::PageDown(page_lines),
+            select,
+        },
+        command => command,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(text: &str) -> Chord {
+        Chord::parse(text).unwrap()
+    }
+
+    fn command(name: &str) -> KeyPress {
+        KeyPress::Action(KeyAction::parse(name).unwrap())
+    }
+
+    #[test]
+    fn parse_chords() {
+        let parsed = chord("Shift+Ctrl+Z");
+        assert_eq!(parsed.to_string(), "ctrl+shift+z");
+        assert_eq!(chord("ctrl++").to_string(), "ctrl++");
+        assert_eq!(chord("cmd+esc").to_string(), "meta+escape");
+        assert_eq!(
+            Chord::from_key(&Key::Character("Z".to_string()), Modifiers::CONTROL),
+            Some(chord("ctrl+z"))
+        );
+        assert_eq!(
+            Chord::from_key(&Key::Character(" ".to_string()), Modifiers::empty()),
+            Some(chord("space"))
+        );
+        assert_eq!(Chord::from_key(&Key::Shift, Modifiers::SHIFT), None);
+    }
+
+    #[test]
+    fn override_and_unbind() {
+        let mut keymap = Keymap::from_toml(
+            r#"
+            [bindings]
+            "ctrl+d" = "duplicate_lines"
+            "ctrl+shift+d" = ""
+            "ctrl+k ctrl+c" = "toggle_line_comment"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(keymap.status(), "");
+        assert_eq!(keymap.press(chord("ctrl+d")), command("duplicate_lines"));
+        assert_eq!(keymap.press(chord("ctrl+shift+d")), KeyPress::Unbound);
+        assert_eq!(keymap.press(chord("ctrl+k")), KeyPress::Pending);
+        assert_eq!(
+            keymap.press(chord("ctrl+c")),
+            command("toggle_line_comment")
+        );
+        // 其他默认绑定保持不变
+        assert_eq!(keymap.press(chord("ctrl+z")), command("undo"));
+        assert_eq!(
+            keymap.binding_for(
+                &KeyAction::parse("add_next_occurrence").unwrap(),
+                KeymapProfile::Default
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn duplicate_bindings_are_reported() {
+        let keymap = Keymap::from_toml(
+            r#"
+            [bindings]
+            "ctrl+e" = "copy"
+            "CTRL+E" = "copy"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(keymap.status(), "");
+
+        let mut keymap = Keymap::from_toml(
+            r#"
+            [bindings]
+            "ctrl+e" = "copy"
+            "Ctrl+E" = "cut"
+            "#,
+        )
+        .unwrap();
+        assert!(keymap.status().contains("ctrl+e 被重复绑定"));
+        assert!(matches!(keymap.press(chord("ctrl+e")), KeyPress::Action(_)));
+    }
+
+    #[test]
+    fn prefix_conflicts() {
+        // 与默认绑定冲突时去掉默认绑定
+        let mut keymap = Keymap::from_toml("[bindings]\n\"ctrl+k\" = \"copy\"").unwrap();
+        assert!(keymap.status().contains("覆盖了默认绑定 ctrl+k ctrl+r"));
+        assert_eq!(keymap.press(chord("ctrl+k")), command("copy"));
+        assert_eq!(
+            keymap.binding_for(&KeyAction::ReloadKeymap, KeymapProfile::Default),
+            None
+        );
+
+        // 配置之间冲突时保留较短的序列
+        let mut keymap = Keymap::from_toml(
+            r#"
+            [bindings]
+            "ctrl+e ctrl+e" = "cut"
+            "ctrl+e" = "copy"
+            "#,
+        )
+        .unwrap();
+        assert!(keymap.status().contains("ctrl+e ctrl+e 与 ctrl+e 冲突"));
+        assert_eq!(keymap.press(chord("ctrl+e")), command("copy"));
+        assert_eq!(keymap.press(chord("ctrl+e")), command("copy"));
+    }
+
+    #[test]
+    fn invalid_config() {
+        for (text, error) in [
+            ("[bindings]\n\"ctrl+e\" = \"frobnicate\"", "frobnicate"),
+            ("[bindings]\n\"ctrl+\" = \"copy\"", "缺少按键"),
+            ("[bindings]\n\"hyper+e\" = \"copy\"", "未知的修饰键：hyper"),
+            ("[bindings]\n\" \" = \"copy\"", "按键序列为空"),
+            ("[bindings]\n\"ctrl+e\" = 1", "的值必须是命令名称"),
+            ("bindings = 1", "bindings 必须是表"),
+        ] {
+            let err = Keymap::from_toml(text).unwrap_err();
+            assert!(err.contains(error), "{:?}: {}", text, err);
+        }
+        assert!(Keymap::from_toml("[bindings").is_err());
+        // 没有 bindings 表时使用默认绑定
+        let mut keymap = Keymap::from_toml("").unwrap();
+        assert_eq!(keymap.press(chord("ctrl+z")), command("undo"));
+    }
+
+    #[test]
+    fn sequences_wait_for_the_next_key() {
+        let mut keymap = Keymap::default();
+        assert_eq!(keymap.press(chord("ctrl+k")), KeyPress::Pending);
+        assert_eq!(keymap.status(), "ctrl+k …");
+        // 后续按键没有绑定时整个序列作废，按键不再单独处理
+        assert_eq!(keymap.press(chord("ctrl+z")), KeyPress::Cancelled);
+        assert_eq!(keymap.status(), "");
+        assert_eq!(keymap.press(chord("ctrl+z")), command("undo"));
+        assert_eq!(keymap.press(chord("ctrl+k")), KeyPress::Pending);
+        assert_eq!(keymap.press(chord("ctrl+r")), command("reload_keymap"));
+        assert_eq!(keymap.press(chord("ctrl+alt+q")), KeyPress::Unbound);
+    }
+
+    #[test]
+    fn bindings_shown_per_profile() {
+        let keymap = Keymap::default();
+        let binding =
+            |name: &str, profile| keymap.binding_for(&KeyAction::parse(name).unwrap(), profile);
+        use KeymapProfile::*;
+        assert_eq!(binding("select_all", Default).as_deref(), Some("ctrl+a"));
+        assert_eq!(binding("select_all", Emacs), None);
+        assert_eq!(binding("find", Emacs), None);
+        assert_eq!(binding("toggle_line_comment", Emacs), None);
+        assert_eq!(binding("undo", Emacs).as_deref(), Some("ctrl+z"));
+        assert_eq!(binding("redo", Default).as_deref(), Some("ctrl+y"));
+        assert_eq!(binding("redo", Emacs).as_deref(), Some("ctrl+shift+z"));
+        assert_eq!(binding("newline", Vim), None);
+        assert_eq!(binding("move page_down", Vim).as_deref(), Some("pagedown"));
+        // 序列中任何一个按键被占用都无法触发
+        assert_eq!(binding("reload_keymap", Vim).as_deref(), None);
+        assert_eq!(binding("reload_keymap", Emacs), None);
+        // 界面面板的按键在各方案下都可用
+        for profile in KeymapProfile::ALL {
+            assert_eq!(
+                binding("command_palette", profile).as_deref(),
+                Some("ctrl+shift+p")
+            );
+            assert_eq!(
+                binding("macro_panel", profile).as_deref(),
+                Some("ctrl+shift+m")
+            );
+            assert!(binding("toggle_macro_recording", profile).is_some());
+        }
+    }
+}
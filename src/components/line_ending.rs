@@ -0,0 +1,171 @@
+use std::borrow::Cow;
+
+/// 文件的换行符。编辑时文本统一使用 `\n`，保存时转换为文件原来的换行符；
+/// 混用多种换行符的文件保存时统一为出现最多的一种
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+    Cr,
+}
+
+impl LineEnding {
+    pub const ALL: [LineEnding; 3] = [LineEnding::Lf, LineEnding::Crlf, LineEnding::Cr];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+
+    /// 命令参数中的名称，如 `crlf`
+    pub fn name(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "lf",
+            LineEnding::Crlf => "crlf",
+            LineEnding::Cr => "cr",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        LineEnding::ALL.into_iter().find(|e| e.name() == name)
+    }
+
+    /// 状态栏中显示的名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "LF",
+            LineEnding::Crlf => "CRLF",
+            LineEnding::Cr => "CR",
+        }
+    }
+
+    /// 文本中出现最多的换行符，数量相同或没有换行时为 LF
+    pub fn detect(text: &str) -> Self {
+        let [lf, crlf, cr] = Self::count(text);
+        if crlf > lf && crlf >= cr {
+            LineEnding::Crlf
+        } else if cr > lf && cr > crlf {
+            LineEnding::Cr
+        } else {
+            LineEnding::Lf
+        }
+    }
+
+    /// 文本是否混用了多种换行符
+    pub fn is_mixed(text: &str) -> bool {
+        Self::count(text).into_iter().filter(|&n| n > 0).count() > 1
+    }
+
+    /// LF、CRLF、CR 各自出现的次数
+    fn count(text: &str) -> [usize; 3] {
+        let (mut lf, mut crlf, mut cr) = (0, 0, 0);
+        let mut chars = text.chars().peekable();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\r' if chars.peek() == Some(&'\n') => {
+                    chars.next();
+                    crlf += 1;
+                }
+                '\r' => cr += 1,
+                '\n' => lf += 1,
+                _ => {}
+            }
+        }
+        [lf, crlf, cr]
+    }
+
+    /// 把各种换行符统一为 `\n`
+    pub fn normalize(text: &str) -> Cow<'_, str> {
+        if text.contains('\r') {
+            Cow::Owned(text.replace("\r\n", "\n").replace('\r', "\n"))
+        } else {
+            Cow::Borrowed(text)
+        }
+    }
+
+    /// 把以 `\n` 换行的文本转换为此换行符
+    pub fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match self {
+            LineEnding::Lf => Cow::Borrowed(text),
+            _ => Cow::Owned(text.replace('\n', self.as_str())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect() {
+        for (text, expected) in [
+            ("", LineEnding::Lf),
+            ("abc", LineEnding::Lf),
+            ("a\nb\n", LineEnding::Lf),
+            ("a\r\nb\r\n", LineEnding::Crlf),
+            ("a\rb\r", LineEnding::Cr),
+            ("a\r\nb\r\nc\n", LineEnding::Crlf),
+            ("a\nb\nc\r\n", LineEnding::Lf),
+            ("a\r\nb\n", LineEnding::Lf),
+            ("a\rb\r\n", LineEnding::Crlf),
+            ("a\r\r\n", LineEnding::Crlf),
+        ] {
+            assert_eq!(LineEnding::detect(text), expected, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn is_mixed() {
+        for (text, expected) in [
+            ("", false),
+            ("a\nb\n", false),
+            ("a\r\nb\r\n", false),
+            ("a\rb", false),
+            ("a\r\nb\n", true),
+            ("a\rb\n", true),
+            ("a\r\r\n", true),
+        ] {
+            assert_eq!(LineEnding::is_mixed(text), expected, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn normalize() {
+        for (text, expected) in [
+            ("", ""),
+            ("a\nb", "a\nb"),
+            ("a\r\nb\r\n", "a\nb\n"),
+            ("a\rb\r", "a\nb\n"),
+            ("a\r\nb\rc\nd", "a\nb\nc\nd"),
+            ("a\r\r\n", "a\n\n"),
+        ] {
+            assert_eq!(LineEnding::normalize(text), expected, "{text:?}");
+        }
+        assert!(matches!(LineEnding::normalize("a\nb"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn apply() {
+        for (ending, expected) in [
+            (LineEnding::Lf, "a\nb\n"),
+            (LineEnding::Crlf, "a\r\nb\r\n"),
+            (LineEnding::Cr, "a\rb\r"),
+        ] {
+            assert_eq!(ending.apply("a\nb\n"), expected, "{ending:?}");
+            assert_eq!(ending.apply(""), "");
+            assert_eq!(LineEnding::normalize(&ending.apply("a\nb\n")), "a\nb\n");
+        }
+    }
+
+    #[test]
+    fn names() {
+        for ending in LineEnding::ALL {
+            assert_eq!(LineEnding::from_name(ending.name()), Some(ending));
+        }
+        assert_eq!(LineEnding::from_name("CRLF"), None);
+    }
+}
@@ -0,0 +1,119 @@
+use super::clipboard::write_clipboard;
+use super::editor_new::Editor;
+use super::macros::{list_macros, load_macro, save_macro, Macro};
+use dioxus::prelude::*;
+
+#[derive(Props, PartialEq, Clone)]
+pub struct MacroPanelProps {
+    editor: Signal<Editor>,
+    /// 最近录制或载入的宏
+    recorded: Signal<Macro>,
+    on_close: EventHandler<()>,
+}
+
+/// 宏面板(Ctrl+Shift+M)：回放若干次，保存或载入命名的宏
+#[component]
+pub fn MacroPanel(props: MacroPanelProps) -> Element {
+    let mut editor = props.editor;
+    let mut recorded = props.recorded;
+    let mut times = use_signal(|| "1".to_string());
+    let mut name = use_signal(String::new);
+    let mut saved = use_signal(list_macros);
+    let mut status = use_signal(String::new);
+
+    let mut play = move || {
+        let Some(count) = times().trim().parse::<usize>().ok().filter(|n| *n > 0) else {
+            status.set("次数必须是正整数".to_string());
+            return;
+        };
+        let current = recorded();
+        if let Some(text) = editor.with_mut(|e| e.play_macro(&current, count)) {
+            spawn(write_clipboard(text));
+        }
+        props.on_close.call(());
+    };
+    let on_keydown = move |e: Event<KeyboardData>| match e.key() {
+        Key::Enter => {
+            e.prevent_default();
+            play();
+        }
+        Key::Escape => props.on_close.call(()),
+        _ => {}
+    };
+
+    let commands = recorded.with(|m| m.commands.len());
+
+    rsx! {
+        div {
+            style: "position: fixed; top: 48px; left: 50%; transform: translateX(-50%); z-index: 10; background: #f0f0f0; border: 1px solid #ddd; padding: 4px; font-size: 14px;",
+            div {
+                style: "display: flex; align-items: center;",
+                span { style: "margin-right: 8px;", "当前宏共 {commands} 条命令，回放" }
+                input {
+                    style: "width: 48px;",
+                    value: "{times}",
+                    oninput: move |e| times.set(e.value()),
+                    onkeydown: on_keydown,
+                    onmounted: move |e| {
+                        spawn(async move {
+                            let _ = e.set_focus(true).await;
+                        });
+                    },
+                }
+                span { style: "margin: 0 8px;", "次" }
+                button { title: "回放 (Enter)", onclick: move |_| play(), "回放" }
+                button {
+                    style: "margin-left: 4px;",
+                    title: "关闭 (Escape)",
+                    onclick: move |_| props.on_close.call(()),
+                    "×"
+                }
+            }
+            div {
+                style: "display: flex; align-items: center; margin-top: 4px;",
+                input {
+                    style: "width: 160px;",
+                    placeholder: "宏名称",
+                    value: "{name}",
+                    oninput: move |e| name.set(e.value()),
+                    onkeydown: move |e: Event<KeyboardData>| {
+                        if e.key() == Key::Escape {
+                            props.on_close.call(());
+                        }
+                    },
+                }
+                button {
+                    style: "margin-left: 4px;",
+                    onclick: move |_| match save_macro(name().trim(), &recorded()) {
+                        Ok(()) => {
+                            saved.set(list_macros());
+                            status.set(format!("已保存 {}", name().trim()));
+                        }
+                        Err(err) => status.set(format!("保存失败：{}", err)),
+                    },
+                    "保存"
+                }
+            }
+            for saved_name in saved() {
+                div {
+                    style: "display: flex; align-items: center; margin-top: 4px;",
+                    span { style: "flex: 1;", "{saved_name}" }
+                    button {
+                        onclick: move |_| match load_macro(&saved_name) {
+                            Ok(loaded) => {
+                                status.set(format!("已载入 {}", saved_name));
+                                name.set(saved_name.clone());
+                                recorded.set(loaded);
+                            }
+                            Err(err) => status.set(format!("载入失败：{}", err)),
+                        },
+                        "载入"
+                    }
+                }
+            }
+            if !status().is_empty() {
+                div { style: "margin-top: 4px; color: gray;", "{status}" }
+            }
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use super::command::EditorCommand;
+use super::config::config_dir;
+use super::editor_new::Editor;
+use std::io;
+use std::path::PathBuf;
+
+/// 录制下来的一串编辑命令
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Macro {
+    pub commands: Vec<EditorCommand>,
+}
+
+impl Macro {
+    /// 每行一条命令的文本形式，用于保存到磁盘
+    pub fn to_text(&self) -> String {
+        self.commands
+            .iter()
+            .map(|command| format!("{}\n", command))
+            .collect()
+    }
+
+    /// 解析 `to_text` 的结果，忽略空行和 `#` 开头的注释
+    pub fn from_text(text: &str) -> io::Result<Self> {
+        let commands = text
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                line.parse()
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Macro { commands })
+    }
+}
+
+/// 保存宏的目录 `~/.config/editor-x/macros`
+fn macro_dir() -> io::Result<PathBuf> {
+    config_dir()
+        .map(|dir| dir.join("macros"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "找不到用户目录"))
+}
+
+/// 宏名称只允许字母、数字、`-` 和 `_`，避免写到目录之外
+fn macro_path(name: &str) -> io::Result<PathBuf> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "宏名称只能包含字母、数字、- 和 _",
+        ));
+    }
+    Ok(macro_dir()?.join(format!("{}.macro", name)))
+}
+
+pub fn save_macro(name: &str, recorded: &Macro) -> io::Result<()> {
+    let path = macro_path(name)?;
+    std::fs::create_dir_all(macro_dir()?)?;
+    std::fs::write(path, recorded.to_text())
+}
+
+pub fn load_macro(name: &str) -> io::Result<Macro> {
+    Macro::from_text(&std::fs::read_to_string(macro_path(name)?)?)
+}
+
+/// 已保存的宏名称，按字母排序
+pub fn list_macros() -> Vec<String> {
+    let Ok(entries) = macro_dir().and_then(std::fs::read_dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "macro" {
+                return None;
+            }
+            Some(path.file_stem()?.to_string_lossy().into_owned())
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+impl Editor {
+    /// 回放宏 times 次。录制时只录入相对光标的命令(鼠标定位不录入，
+    /// Vim 的动作按键也从光标处解释)，因此从当前光标处开始生效。
+    /// 全部回放作为一次撤销。返回最后一次复制或剪切的文本
+    pub fn play_macro(&mut self, recorded: &Macro, times: usize) -> Option<String> {
+        let mut clipboard = None;
+        self.begin_undo_group();
+        for _ in 0..times {
+            for command in &recorded.commands {
+                if let Some(text) = self.execute(command.clone()) {
+                    clipboard = Some(text);
+                }
+            }
+        }
+        self.end_undo_group();
+        clipboard
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::command::Motion;
+    use crate::components::emacs::Emacs;
+    use crate::components::position::Position;
+    use crate::components::vim::Vim;
+    use dioxus::prelude::{Key, Modifiers};
+
+    fn editor(text: &str) -> Editor {
+        let mut editor = Editor::new();
+        editor.execute(EditorCommand::InsertText(text.to_string()));
+        editor.set_cursor_position(Position::new(0, 0));
+        editor
+    }
+
+    fn key(c: &str) -> Key {
+        Key::Character(c.to_string())
+    }
+
+    fn record(editor: &mut Editor, press: impl FnOnce(&mut Editor)) -> Macro {
+        editor.start_command_log();
+        press(editor);
+        let recorded = Macro {
+            commands: editor.take_command_log(),
+        };
+        assert!(!recorded.commands.iter().any(|command| matches!(
+            command,
+            EditorCommand::SetCursor(_) | EditorCommand::SelectTo(_)
+        )));
+        recorded
+    }
+
+    #[test]
+    fn vim_macro_replays_from_cursor() {
+        let mut editor = editor("one two\nthree four\nfive six\n");
+        let mut vim = Vim::default();
+        let recorded = record(&mut editor, |editor| {
+            for c in ["w", "v", "l", "d", "j", "0"] {
+                vim.handle_key(editor, &key(c), Modifiers::empty());
+            }
+        });
+        assert_eq!(editor.get_text(), "one o\nthree four\nfive six\n");
+        editor.play_macro(&Macro::from_text(&recorded.to_text()).unwrap(), 2);
+        assert_eq!(editor.get_text(), "one o\nthree ur\nfive x\n");
+        assert_eq!(editor.get_cursor_position(), Position::new(2, 0));
+    }
+
+    #[test]
+    fn emacs_macro_replays_from_cursor() {
+        let mut editor = editor("a1 b1\na2 b2\na3 b3");
+        let mut emacs = Emacs::default();
+        let recorded = record(&mut editor, |editor| {
+            emacs.handle_key(editor, &key(" "), Modifiers::CONTROL);
+            emacs.handle_key(editor, &key("f"), Modifiers::ALT);
+            emacs.handle_key(editor, &key("w"), Modifiers::CONTROL);
+            emacs.handle_key(editor, &key("n"), Modifiers::CONTROL);
+        });
+        assert_eq!(editor.get_text(), " b1\na2 b2\na3 b3");
+        editor.play_macro(&recorded, 2);
+        assert_eq!(editor.get_text(), " b1\n b2\n b3");
+    }
+
+    #[test]
+    fn playback_is_one_undo_step() {
+        let mut editor = editor("a\nb\nc");
+        editor.type_char('x');
+        let recorded = Macro {
+            commands: vec![
+                EditorCommand::Move {
+                    motion: Motion::LineEnd,
+                    select: false,
+                },
+                EditorCommand::TypeChar(';'),
+                EditorCommand::Move {
+                    motion: Motion::Down,
+                    select: false,
+                },
+            ],
+        };
+        editor.play_macro(&recorded, 3);
+        assert_eq!(editor.get_text(), "xa;\nb;\nc;");
+        editor.undo();
+        assert_eq!(editor.get_text(), "xa\nb\nc");
+        editor.redo();
+        assert_eq!(editor.get_text(), "xa;\nb;\nc;");
+    }
+}
@@ -1,7 +1,30 @@
+mod clipboard;
+mod command;
+mod command_palette;
+mod config;
 mod editor_new;
+mod emacs;
+mod find_bar;
+mod goto_line;
+mod grapheme;
+mod history;
+mod indent;
+mod keymap;
+mod line_ending;
+mod macro_panel;
+mod macros;
 mod mouse_event;
+mod position;
+mod registry;
+mod search;
+mod selection;
 mod toolbar;
+mod vim;
+mod word;
 
 pub use editor_new::EditorArea;
+pub use indent::IndentConfig;
+pub use keymap::KeymapProfile;
 pub use mouse_event::handle_mouse_click;
+pub use position::Position;
 pub use toolbar::Toolbar;
@@ -1,51 +1,83 @@
+use super::editor_new::{last_line, line_len};
+use super::grapheme::{advance_column, line_graphemes};
+use super::position::Position;
 use dioxus::prelude::*;
 use ropey::Rope;
 
+/// visible_lines 为未被折叠的行，第 n 个显示行对应 visible_lines[n]
+#[allow(clippy::too_many_arguments)]
 pub fn handle_mouse_click(
     e: MouseEvent,
-    mut cursor_position: Signal<(usize, usize)>,
+    mut cursor_position: Signal<Position>,
     line_height: usize,
     char_width: usize,
+    gutter_width: usize,
+    tab_width: usize,
+    scroll_top: usize,
+    visible_lines: &[usize],
     rope: &Rope,
 ) {
     // 获取鼠标点击位置
     let coordinates = e.page_coordinates();
-    let mouse_x = coordinates.x.round() as usize;
-    let mouse_y = coordinates.y.round() as usize;
+    let mouse_x = (coordinates.x.round() as usize).saturating_sub(gutter_width);
+    let mouse_y = coordinates.y.round() as usize + scroll_top;
 
     // 将像素位置转换为行和列位置
-    let mut line = (mouse_y - 41) / line_height;
-    let mut col = 0;
-    let mut width = 0;
-
-    // 获取最大行号
-    let max_lines = rope.len_lines();
-    if line >= max_lines - 1 {
-        line = max_lines - 2;
+    let row = mouse_y.saturating_sub(41) / line_height;
+    let mut line = visible_lines
+        .get(row)
+        .or(visible_lines.last())
+        .copied()
+        .unwrap_or(row);
+
+    // 不超过最后一行(文本以换行结尾时，其后的空行不算)
+    line = line.min(last_line(rope));
+
+    let line_text = rope.line(line).to_string();
+    let x = mouse_x.saturating_sub(8);
+    let col = hit_column(&line_text, x, char_width, tab_width).unwrap_or(line_len(rope, line));
+
+    // 更新光标位置
+    cursor_position.set(Position::new(line, col));
+    println!("Cursor position: {:?}", cursor_position());
+}
+
+/// 行内横坐标 x 处的列，按字素簇命中，光标不会落在簇的中间；超出行尾时为 None
+fn hit_column(line_text: &str, x: usize, char_width: usize, tab_width: usize) -> Option<usize> {
+    let mut column = 0;
+    for (i, grapheme) in line_graphemes(line_text) {
+        column = advance_column(column, grapheme, tab_width);
+        if column * char_width > x {
+            return Some(i);
+        }
     }
-    println!("Max lines: {}", max_lines);
-
-    if line < max_lines {
-        let line_text = rope.line(line);
-        let max_cols = line_text.len_chars();
-        for (i, ch) in line_text.chars().enumerate() {
-            width += if ch.is_ascii() {
-                char_width
-            } else {
-                char_width * 2
-            };
-            if width > mouse_x - 8 {
-                col = i;
-                break;
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hit_narrow_non_ascii_text() {
+        // é、ü、Cyrillic 和 Greek 都只占一列
+        for line in ["héllo wörld", "привет мир", "αβγδε ζηθ"] {
+            for col in 0..line.chars().count() {
+                assert_eq!(hit_column(line, col * 10 + 5, 10, 4), Some(col), "{line:?}");
             }
-        }
-        // 如果列号超出当前行的最大位置，设置列号为行尾
-        if mouse_x - 8 >= width {
-            col = max_cols - 1;
+            assert_eq!(hit_column(line, line.chars().count() * 10, 10, 4), None);
         }
     }
 
-    // 更新光标位置
-    cursor_position.set((line, col));
-    println!("Cursor position: {:?}", cursor_position());
+    #[test]
+    fn hit_wide_and_tab_text() {
+        // 中、文各占两列，制表符对齐到第 4 列
+        let line = "中文a\tb";
+        for (x, expected) in [(5, 0), (15, 0), (25, 1), (45, 2), (55, 3), (75, 3), (85, 4)] {
+            assert_eq!(hit_column(line, x, 10, 4), Some(expected), "{x}");
+        }
+        assert_eq!(hit_column(line, 90, 10, 4), None);
+        // 组合字符和字素簇一起命中
+        assert_eq!(hit_column("e\u{301}x", 15, 10, 4), Some(2));
+    }
 }
@@ -0,0 +1,148 @@
+use super::editor_new::line_len;
+use ropey::Rope;
+
+/// 文本中的位置：行号和行内的字符列，均从 0 开始
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    pub line: usize,
+    pub col: usize,
+}
+
+/// 从文本开头计算的偏移量，按不同的单位计数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Offset {
+    /// Unicode 标量值(ropey 的字符下标)
+    Char(usize),
+    /// UTF-8 字节，编译器诊断使用
+    Utf8(usize),
+    /// UTF-16 码元，LSP 默认使用
+    Utf16(usize),
+}
+
+impl Offset {
+    /// 换算为字符偏移量，超出文本长度时取文本末尾
+    pub fn to_char(self, rope: &Rope) -> usize {
+        match self {
+            Offset::Char(idx) => idx.min(rope.len_chars()),
+            Offset::Utf8(byte) => rope.byte_to_char(byte.min(rope.len_bytes())),
+            Offset::Utf16(unit) => rope.utf16_cu_to_char(unit.min(rope.len_utf16_cu())),
+        }
+    }
+
+    /// 偏移量的数值
+    pub fn value(self) -> usize {
+        match self {
+            Offset::Char(n) | Offset::Utf8(n) | Offset::Utf16(n) => n,
+        }
+    }
+
+    /// 同一单位的另一个偏移量
+    pub fn with_value(self, value: usize) -> Self {
+        match self {
+            Offset::Char(_) => Offset::Char(value),
+            Offset::Utf8(_) => Offset::Utf8(value),
+            Offset::Utf16(_) => Offset::Utf16(value),
+        }
+    }
+
+    /// 同一单位下 pos 处的偏移量
+    pub fn at(self, rope: &Rope, pos: Position) -> Self {
+        match self {
+            Offset::Char(_) => Offset::Char(pos.char_offset(rope)),
+            Offset::Utf8(_) => Offset::Utf8(pos.byte_offset(rope)),
+            Offset::Utf16(_) => Offset::Utf16(pos.utf16_offset(rope)),
+        }
+    }
+}
+
+impl Position {
+    pub fn new(line: usize, col: usize) -> Self {
+        Position { line, col }
+    }
+
+    /// 任意单位的偏移量对应的位置
+    pub fn from_offset(rope: &Rope, offset: Offset) -> Self {
+        let idx = offset.to_char(rope);
+        let line = rope.char_to_line(idx);
+        Position::new(line, idx - rope.line_to_char(line))
+    }
+
+    /// 字符偏移量，行或列超出范围时取最近的有效位置
+    pub fn char_offset(self, rope: &Rope) -> usize {
+        let line = self.line.min(rope.len_lines() - 1);
+        rope.line_to_char(line) + self.col.min(line_len(rope, line))
+    }
+
+    /// UTF-8 字节偏移量
+    pub fn byte_offset(self, rope: &Rope) -> usize {
+        rope.char_to_byte(self.char_offset(rope))
+    }
+
+    /// UTF-16 码元偏移量
+    pub fn utf16_offset(self, rope: &Rope) -> usize {
+        rope.char_to_utf16_cu(self.char_offset(rope))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 中：1 个字符、3 个字节、1 个 UTF-16 码元
+    // 😀：1 个字符、4 个字节、2 个 UTF-16 码元(代理对)
+    const TEXT: &str = "a中😀b\n😀\n";
+
+    #[test]
+    fn offsets_of_positions() {
+        let rope = Rope::from_str(TEXT);
+        for (pos, char, utf8, utf16) in [
+            (Position::new(0, 0), 0, 0, 0),
+            (Position::new(0, 1), 1, 1, 1),
+            (Position::new(0, 2), 2, 4, 2),
+            (Position::new(0, 3), 3, 8, 4),
+            (Position::new(0, 4), 4, 9, 5),
+            (Position::new(1, 0), 5, 10, 6),
+            (Position::new(1, 1), 6, 14, 8),
+            (Position::new(2, 0), 7, 15, 9),
+        ] {
+            assert_eq!(pos.char_offset(&rope), char, "{pos:?}");
+            assert_eq!(pos.byte_offset(&rope), utf8, "{pos:?}");
+            assert_eq!(pos.utf16_offset(&rope), utf16, "{pos:?}");
+            assert_eq!(Position::from_offset(&rope, Offset::Char(char)), pos);
+            assert_eq!(Position::from_offset(&rope, Offset::Utf8(utf8)), pos);
+            assert_eq!(Position::from_offset(&rope, Offset::Utf16(utf16)), pos);
+        }
+    }
+
+    #[test]
+    fn columns_past_the_line_end_are_clamped() {
+        let rope = Rope::from_str(TEXT);
+        assert_eq!(Position::new(0, 99).char_offset(&rope), 4);
+        assert_eq!(Position::new(1, 99).byte_offset(&rope), 14);
+        assert_eq!(Position::new(1, 99).utf16_offset(&rope), 8);
+        assert_eq!(Position::new(99, 99).char_offset(&rope), 7);
+    }
+
+    #[test]
+    fn offsets_past_the_end_are_clamped() {
+        let rope = Rope::from_str(TEXT);
+        let end = Position::new(2, 0);
+        assert_eq!(Position::from_offset(&rope, Offset::Char(99)), end);
+        assert_eq!(Position::from_offset(&rope, Offset::Utf8(99)), end);
+        assert_eq!(Position::from_offset(&rope, Offset::Utf16(99)), end);
+    }
+
+    #[test]
+    fn offsets_inside_a_character_round_down() {
+        let rope = Rope::from_str(TEXT);
+        // 字节偏移落在中的中间、UTF-16 偏移落在代理对的中间
+        assert_eq!(
+            Position::from_offset(&rope, Offset::Utf8(2)),
+            Position::new(0, 1)
+        );
+        assert_eq!(
+            Position::from_offset(&rope, Offset::Utf16(3)),
+            Position::new(0, 2)
+        );
+    }
+}
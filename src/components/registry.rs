@@ -0,0 +1,220 @@
+use super::command::{EditorCommand, Motion, SIMPLE_COMMANDS};
+use super::keymap::{KeyAction, KeymapProfile, UI_ACTIONS};
+use super::line_ending::LineEnding;
+use super::IndentConfig;
+use crate::language::Language;
+use std::path::PathBuf;
+
+/// 命令面板可以执行的操作
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaletteAction {
+    /// 可以绑定到按键的编辑命令和界面操作
+    Key(KeyAction),
+    NewFile,
+    /// 路径在命令面板中输入
+    OpenFile(PathBuf),
+    SaveFile,
+    SaveFileAs(PathBuf),
+    SetLanguage(Language),
+    SetKeymapProfile(KeymapProfile),
+    SetIndent(IndentConfig),
+}
+
+impl PaletteAction {
+    /// 需要输入路径的操作返回输入框的提示
+    pub fn prompt(&self) -> Option<&'static str> {
+        match self {
+            PaletteAction::OpenFile(_) => Some("要打开的文件路径"),
+            PaletteAction::SaveFileAs(_) => Some("另存为的文件路径"),
+            _ => None,
+        }
+    }
+
+    /// 填入输入的路径
+    pub fn with_argument(self, argument: &str) -> Self {
+        let path = PathBuf::from(argument.trim());
+        match self {
+            PaletteAction::OpenFile(_) => PaletteAction::OpenFile(path),
+            PaletteAction::SaveFileAs(_) => PaletteAction::SaveFileAs(path),
+            action => action,
+        }
+    }
+}
+
+/// 命令面板中的一项
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaletteEntry {
+    pub title: String,
+    pub action: PaletteAction,
+}
+
+impl PaletteEntry {
+    fn new(title: impl Into<String>, action: PaletteAction) -> Self {
+        PaletteEntry {
+            title: title.into(),
+            action,
+        }
+    }
+
+    /// 模糊匹配的文本：标题加上命令名称，以便用英文名称查找
+    pub fn search_text(&self) -> String {
+        match &self.action {
+            PaletteAction::Key(action) => format!("{} {}", self.title, action),
+            _ => self.title.clone(),
+        }
+    }
+}
+
+/// 命令面板的全部命令。编辑命令和界面操作来自 `SIMPLE_COMMANDS` 和 `UI_ACTIONS`，
+/// 光标移动来自 `Motion::ALL`，换行符、语言、按键方案和缩进来自各自的可选列表，在那里登记的新项会自动出现
+pub fn palette_entries() -> Vec<PaletteEntry> {
+    let mut entries = Vec::new();
+    for (_, action) in UI_ACTIONS {
+        if *action != KeyAction::CommandPalette {
+            entries.push(PaletteEntry::new(
+                action.title(),
+                PaletteAction::Key(action.clone()),
+            ));
+        }
+    }
+    for command in SIMPLE_COMMANDS {
+        let action = KeyAction::Command(command.clone());
+        entries.push(PaletteEntry::new(
+            action.title(),
+            PaletteAction::Key(action),
+        ));
+    }
+    for select in [false, true] {
+        for motion in Motion::ALL {
+            let command = EditorCommand::Move { motion, select };
+            let title = format!("{}：{}", command.title(), motion.title());
+            let action = KeyAction::Command(command);
+            entries.push(PaletteEntry::new(title, PaletteAction::Key(action)));
+        }
+    }
+    for ending in LineEnding::ALL {
+        let title = format!("换行符：转换为 {}", ending.label());
+        let action = KeyAction::Command(EditorCommand::SetLineEnding(ending));
+        entries.push(PaletteEntry::new(title, PaletteAction::Key(action)));
+    }
+    entries.extend([
+        PaletteEntry::new("文件：新建", PaletteAction::NewFile),
+        PaletteEntry::new("文件：打开…", PaletteAction::OpenFile(PathBuf::new())),
+        PaletteEntry::new("文件：保存", PaletteAction::SaveFile),
+        PaletteEntry::new("文件：另存为…", PaletteAction::SaveFileAs(PathBuf::new())),
+    ]);
+    for language in Language::ALL {
+        let title = format!("语言：{}", language.name());
+        entries.push(PaletteEntry::new(
+            title,
+            PaletteAction::SetLanguage(language),
+        ));
+    }
+    for profile in KeymapProfile::ALL {
+        let title = format!("按键方案：{}", profile.label());
+        entries.push(PaletteEntry::new(
+            title,
+            PaletteAction::SetKeymapProfile(profile),
+        ));
+    }
+    for config in IndentConfig::PRESETS {
+        let title = format!("缩进：{}", config.label());
+        entries.push(PaletteEntry::new(title, PaletteAction::SetIndent(config)));
+    }
+    entries
+}
+
+/// 模糊匹配：query 中的字符按顺序出现在 text 中(忽略大小写和空白)时返回分数，
+/// 连续匹配和在单词开头匹配的得分更高
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous = None;
+    for ch in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = (next..text.len()).find(|i| text[*i] == ch)?;
+        score += 1;
+        if found > 0 && previous == Some(found - 1) {
+            score += 5;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous = Some(found);
+        next = found + 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn fuzzy_matches_in_order() {
+        assert_eq!(fuzzy_score("", "查找"), Some(0));
+        assert!(fuzzy_score("fa", "fold_all").is_some());
+        assert!(fuzzy_score("FOLD all", "fold_all").is_some());
+        assert!(fuzzy_score("查找", "查找 find").is_some());
+        assert_eq!(fuzzy_score("af", "fold_all"), None);
+        assert_eq!(fuzzy_score("foldx", "fold_all"), None);
+    }
+
+    #[test]
+    fn fuzzy_prefers_contiguous_and_word_starts() {
+        // f 和 o 连续，o 不在单词开头：1 + 3 + 1 + 5
+        assert_eq!(fuzzy_score("fo", "fold"), Some(10));
+        assert!(fuzzy_score("fo", "fold") > fuzzy_score("fo", "fxo"));
+        assert!(fuzzy_score("ua", "unfold_all") > fuzzy_score("ua", "usual"));
+    }
+
+    #[test]
+    fn palette_lists_registered_commands() {
+        let entries = palette_entries();
+        let has = |action: PaletteAction| entries.iter().any(|e| e.action == action);
+        for command in SIMPLE_COMMANDS {
+            assert!(has(PaletteAction::Key(KeyAction::Command(command.clone()))));
+        }
+        for (_, action) in UI_ACTIONS {
+            let listed = has(PaletteAction::Key(action.clone()));
+            assert_eq!(listed, *action != KeyAction::CommandPalette, "{:?}", action);
+        }
+        for motion in Motion::ALL {
+            for select in [false, true] {
+                let command = EditorCommand::Move { motion, select };
+                assert!(has(PaletteAction::Key(KeyAction::Command(command))));
+            }
+        }
+        for profile in KeymapProfile::ALL {
+            assert!(has(PaletteAction::SetKeymapProfile(profile)));
+        }
+        for language in Language::ALL {
+            assert!(has(PaletteAction::SetLanguage(language)));
+        }
+        assert!(has(PaletteAction::SaveFile));
+        assert!(has(PaletteAction::Key(KeyAction::ToggleWhitespace)));
+        assert!(has(PaletteAction::Key(KeyAction::Command(
+            EditorCommand::FoldAll
+        ))));
+        // 标题用作列表项的 key，不能重复
+        let titles: HashSet<&str> = entries.iter().map(|e| e.title.as_str()).collect();
+        assert_eq!(titles.len(), entries.len());
+    }
+
+    #[test]
+    fn search_text_includes_command_name() {
+        let entry = palette_entries()
+            .into_iter()
+            .find(|e| e.action == PaletteAction::Key(KeyAction::Command(EditorCommand::FoldAll)))
+            .unwrap();
+        assert_eq!(entry.search_text(), "折叠全部 fold_all");
+        assert!(fuzzy_score("foldall", &entry.search_text()).is_some());
+
+        let entry = palette_entries()
+            .into_iter()
+            .find(|e| e.search_text().ends_with("select word_left"))
+            .unwrap();
+        assert_eq!(entry.title, "扩展选区：上一个单词");
+    }
+}
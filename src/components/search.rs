@@ -0,0 +1,188 @@
+use regex::{Regex, RegexBuilder};
+use ropey::Rope;
+use std::ops::Range;
+
+/// 查找条件
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchQuery {
+    pub pattern: String,
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+    /// 按正则表达式匹配，替换文本中可以用 `$1`、`${name}` 引用捕获组
+    pub regex: bool,
+}
+
+impl SearchQuery {
+    fn to_regex(&self) -> Result<Regex, regex::Error> {
+        let mut pattern = if self.regex {
+            self.pattern.clone()
+        } else {
+            regex::escape(&self.pattern)
+        };
+        if self.whole_word {
+            pattern = format!(r"\b(?:{})\b", pattern);
+        }
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!self.case_sensitive)
+            .multi_line(true)
+            .build()
+    }
+
+    /// 查找全部匹配，返回字符偏移区间(跳过空匹配)
+    pub fn find_all(&self, rope: &Rope) -> Result<Vec<Range<usize>>, regex::Error> {
+        Ok(self
+            .replacements(rope, "")?
+            .into_iter()
+            .map(|(range, _)| range)
+            .collect())
+    }
+
+    /// 全部匹配及其替换文本，正则模式下展开替换文本中的捕获组
+    pub fn replacements(
+        &self,
+        rope: &Rope,
+        replace: &str,
+    ) -> Result<Vec<(Range<usize>, String)>, regex::Error> {
+        if self.pattern.is_empty() {
+            return Ok(Vec::new());
+        }
+        let regex = self.to_regex()?;
+        let text = rope.to_string();
+        Ok(regex
+            .captures_iter(&text)
+            .filter_map(|captures| {
+                let m = captures.get(0)?;
+                if m.is_empty() {
+                    return None;
+                }
+                let mut replacement = String::new();
+                if self.regex {
+                    captures.expand(replace, &mut replacement);
+                } else {
+                    replacement.push_str(replace);
+                }
+                Some((
+                    rope.byte_to_char(m.start())..rope.byte_to_char(m.end()),
+                    replacement,
+                ))
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::editor_new::Editor;
+
+    fn query(pattern: &str) -> SearchQuery {
+        SearchQuery {
+            pattern: pattern.to_string(),
+            case_sensitive: true,
+            ..Default::default()
+        }
+    }
+
+    fn find(query: &SearchQuery, text: &str) -> Vec<Range<usize>> {
+        query.find_all(&Rope::from_str(text)).unwrap()
+    }
+
+    #[test]
+    fn plain_text_is_escaped() {
+        assert_eq!(find(&query("a.b"), "a.b axb a.b"), [0..3, 8..11]);
+        assert!(find(&query(""), "abc").is_empty());
+    }
+
+    #[test]
+    fn case_insensitive() {
+        let mut q = query("foo");
+        assert_eq!(find(&q, "Foo foo FOO"), vec![4..7]);
+        q.case_sensitive = false;
+        assert_eq!(find(&q, "Foo foo FOO"), [0..3, 4..7, 8..11]);
+    }
+
+    #[test]
+    fn whole_word() {
+        let mut q = query("foo");
+        q.whole_word = true;
+        assert_eq!(find(&q, "foo food foo_bar (foo)"), [0..3, 18..21]);
+        // 正则的分支整体加上单词边界
+        q.pattern = "ab|cd".to_string();
+        q.regex = true;
+        assert_eq!(find(&q, "ab xcd cd"), [0..2, 7..9]);
+    }
+
+    #[test]
+    fn regex_with_char_offsets() {
+        let mut q = query(r"\d+");
+        q.regex = true;
+        // 中文字符占多个字节，结果按字符计
+        assert_eq!(find(&q, "第1行 第23行"), [1..2, 5..7]);
+        q.pattern = "^b".to_string();
+        assert_eq!(find(&q, "ab\nba"), vec![3..4]);
+    }
+
+    #[test]
+    fn invalid_regex_is_an_error() {
+        let mut q = query("(");
+        q.regex = true;
+        assert!(q.find_all(&Rope::from_str("(")).is_err());
+        // 非正则模式下按原文查找
+        q.regex = false;
+        assert_eq!(find(&q, "f(x)"), vec![1..2]);
+    }
+
+    #[test]
+    fn zero_length_matches_are_skipped() {
+        let mut q = query("x*");
+        q.regex = true;
+        assert_eq!(find(&q, "axxb"), vec![1..3]);
+        q.pattern = "^".to_string();
+        assert!(find(&q, "a\nb").is_empty());
+    }
+
+    #[test]
+    fn replacements_expand_captures() {
+        let mut q = query(r"(\w+)=(\w+)");
+        q.regex = true;
+        let rope = Rope::from_str("a=1, b=2");
+        assert_eq!(
+            q.replacements(&rope, "$2=$1").unwrap(),
+            [(0..3, "1=a".to_string()), (5..8, "2=b".to_string())]
+        );
+        // 非正则模式下 $ 按原文插入
+        q.regex = false;
+        q.pattern = "a".to_string();
+        assert_eq!(
+            q.replacements(&Rope::from_str("a"), "$1").unwrap(),
+            [(0..1, "$1".to_string())]
+        );
+    }
+
+    #[test]
+    fn replace_all_is_one_undo_step() {
+        let mut editor = Editor::with_text("foo Foo foo");
+        let mut q = query("foo");
+        q.case_sensitive = false;
+        editor.set_search_query(Some(q));
+        assert_eq!(editor.search_matches().unwrap().len(), 3);
+        assert_eq!(editor.replace_all("bar"), 3);
+        assert_eq!(editor.get_text(), "bar bar bar");
+        assert_eq!(editor.search_matches().unwrap().len(), 0);
+        editor.undo();
+        assert_eq!(editor.get_text(), "foo Foo foo");
+    }
+
+    #[test]
+    fn replace_all_with_invalid_regex_does_nothing() {
+        let mut editor = Editor::with_text("a(b");
+        editor.set_search_query(Some(SearchQuery {
+            pattern: "(".to_string(),
+            regex: true,
+            ..Default::default()
+        }));
+        assert!(editor.search_matches().is_err());
+        assert_eq!(editor.replace_all("x"), 0);
+        assert_eq!(editor.get_text(), "a(b");
+    }
+}
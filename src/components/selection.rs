@@ -0,0 +1,44 @@
+use super::position::Position;
+
+/// 选区：anchor 为起点(固定端)，head 为光标所在端
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Selection {
+    pub anchor: Position,
+    pub head: Position,
+    /// 连续上下移动时期望保持的显示列，水平移动或编辑后清空
+    pub goal_column: Option<usize>,
+}
+
+impl Selection {
+    pub fn new(anchor: Position, head: Position) -> Self {
+        Selection {
+            anchor,
+            head,
+            goal_column: None,
+        }
+    }
+
+    /// 没有选中内容的单个光标
+    pub fn cursor(pos: Position) -> Self {
+        Selection::new(pos, pos)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.anchor == self.head
+    }
+
+    /// 选区中靠前的一端
+    pub fn start(&self) -> Position {
+        std::cmp::min(self.anchor, self.head)
+    }
+
+    /// 选区中靠后的一端
+    pub fn end(&self) -> Position {
+        std::cmp::max(self.anchor, self.head)
+    }
+
+    /// 检查给定行列的字符是否被选中
+    pub fn contains(&self, pos: Position) -> bool {
+        self.start() <= pos && pos < self.end()
+    }
+}
@@ -1,13 +1,17 @@
+use super::{IndentConfig, KeymapProfile};
+use crate::language::Language;
 use dioxus::prelude::*;
 
 #[derive(Props, PartialEq, Clone)]
 pub struct ToolbarProps {
     language: Signal<String>,
+    indent: Signal<IndentConfig>,
+    keymap: Signal<KeymapProfile>,
 }
 
 #[component]
 pub fn Toolbar(props: ToolbarProps) -> Element {
-    let mut language = props.language.clone(); // 从父组件获取语言状态
+    let mut language = props.language; // 从父组件获取语言状态
 
     // 监听语言选择变化
     let on_language_change = move |e: Event<FormData>| {
@@ -15,6 +19,22 @@ pub fn Toolbar(props: ToolbarProps) -> Element {
         language.set(selected); // 更新语言选择
     };
 
+    // 监听缩进方式变化
+    let mut indent = props.indent;
+    let on_indent_change = move |e: Event<FormData>| {
+        if let Some(config) = IndentConfig::from_option(&e.value()) {
+            indent.set(config);
+        }
+    };
+
+    // 监听按键方案变化
+    let mut keymap = props.keymap;
+    let on_keymap_change = move |e: Event<FormData>| {
+        if let Some(profile) = KeymapProfile::from_option(&e.value()) {
+            keymap.set(profile);
+        }
+    };
+
     rsx! {
         div {
             style: "flex: 0 1 auto; background: #f0f0f0; border-bottom: 1px solid #ddd; width: 100%; height: 40px; display: flex; align-items: center;",
@@ -28,8 +48,27 @@ pub fn Toolbar(props: ToolbarProps) -> Element {
                 style: "margin-left: auto; margin-left: 50px;",
                 value: "{language}",
                 onchange: on_language_change,
-                option { value: "Rust", "Rust" }
-                option { value: "Markdown", "Markdown" }
+                for language in Language::ALL {
+                    option { value: language.name(), "{language.name()}" }
+                }
+            }
+
+            select {
+                style: "margin-left: 8px;",
+                value: "{indent().to_option()}",
+                onchange: on_indent_change,
+                for config in IndentConfig::PRESETS {
+                    option { value: config.to_option(), "{config.label()}" }
+                }
+            }
+
+            select {
+                style: "margin-left: 8px;",
+                value: "{keymap().to_option()}",
+                onchange: on_keymap_change,
+                for profile in KeymapProfile::ALL {
+                    option { value: profile.to_option(), "{profile.label()}" }
+                }
             }
 
             input {
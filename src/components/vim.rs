@@ -0,0 +1,1415 @@
+use super::command::{EditorCommand, Motion};
+use super::editor_new::{char_to_cursor, last_line, line_end, line_len, Editor};
+use super::position::Position;
+use super::word::CharClass;
+use dioxus::prelude::*;
+use ropey::Rope;
+use std::ops::Range;
+use std::path::PathBuf;
+
+/// Vim 的编辑模式
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum VimMode {
+    #[default]
+    Normal,
+    Insert,
+    Visual,
+    VisualLine,
+}
+
+impl VimMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            VimMode::Normal => "-- NORMAL --",
+            VimMode::Insert => "-- INSERT --",
+            VimMode::Visual => "-- VISUAL --",
+            VimMode::VisualLine => "-- VISUAL LINE --",
+        }
+    }
+}
+
+/// 按键交给 Vim 处理后的结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VimOutcome {
+    Handled,
+    /// 交给默认的按键绑定处理(插入模式下的大部分按键)
+    Unhandled,
+    /// `:q`，需要关闭窗口
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum VimMotion {
+    Left,
+    Right,
+    Down,
+    Up,
+    WordStart,
+    WordBack,
+    WordEnd,
+    LineStart,
+    FirstNonBlank,
+    LineEnd,
+    FirstLine,
+    LastLine,
+    FindForward(char),
+    TillForward(char),
+    FindBackward(char),
+    TillBackward(char),
+}
+
+/// 动作作用于操作符时的范围类型
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MotionKind {
+    Exclusive,
+    Inclusive,
+    Linewise,
+}
+
+impl VimMotion {
+    fn kind(&self) -> MotionKind {
+        match self {
+            VimMotion::Down | VimMotion::Up | VimMotion::FirstLine | VimMotion::LastLine => {
+                MotionKind::Linewise
+            }
+            VimMotion::WordEnd
+            | VimMotion::LineEnd
+            | VimMotion::FindForward(_)
+            | VimMotion::TillForward(_) => MotionKind::Inclusive,
+            _ => MotionKind::Exclusive,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+impl Operator {
+    fn key(&self) -> char {
+        match self {
+            Operator::Delete => 'd',
+            Operator::Change => 'c',
+            Operator::Yank => 'y',
+        }
+    }
+
+    /// 可视模式下作用于选区的按键
+    fn from_visual_key(ch: char) -> Option<Operator> {
+        match ch {
+            'd' | 'x' => Some(Operator::Delete),
+            'c' | 's' => Some(Operator::Change),
+            'y' => Some(Operator::Yank),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum InsertAt {
+    Before,
+    After,
+    LineStart,
+    LineEnd,
+    LineBelow,
+    LineAbove,
+}
+
+/// 普通模式的命令
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NormalCommand {
+    Move(VimMotion),
+    /// 操作符作用于动作的范围，None 表示重复操作符(dd、cc、yy 和 S)作用于整行
+    Operate(Operator, Option<VimMotion>),
+    DeleteChar,
+    Put {
+        before: bool,
+    },
+    Insert(InsertAt),
+    Undo,
+    Repeat,
+    Visual,
+    VisualLine,
+    CommandLine,
+}
+
+#[derive(Debug, PartialEq)]
+enum Parsed<T> {
+    Incomplete,
+    Invalid,
+    Complete(T),
+}
+
+/// 寄存器内容，linewise 表示整行复制
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Register {
+    text: String,
+    linewise: bool,
+}
+
+/// 最近一次修改：触发它的按键和随后在插入模式下执行的命令，供 `.` 重复
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Change {
+    keys: String,
+    inserted: Vec<EditorCommand>,
+}
+
+/// 叠加在 `Editor` 之上的 Vim 模式编辑层
+#[derive(Debug, Default)]
+pub struct Vim {
+    mode: VimMode,
+    /// 尚未构成完整命令的按键(计数、操作符等)
+    pending: String,
+    /// 正在输入的 `:` 命令
+    command_line: Option<String>,
+    message: String,
+    register: Register,
+    last_change: Option<Change>,
+    /// 正在执行 `.`，此时不更新 last_change
+    repeating: bool,
+    /// 当前修改已开始撤销组，回到普通模式时结束
+    undo_group: bool,
+    /// 进入可视模式时的光标位置
+    visual_anchor: Position,
+    /// 进入可视模式后的按键(以 `v` 或 `V` 开头)，选区由它们从 visual_anchor 算出
+    visual_keys: String,
+}
+
+impl Vim {
+    /// 状态栏文本：正在输入的 `:` 命令，或模式、未完成的按键和提示信息
+    pub fn status(&self) -> String {
+        if let Some(line) = &self.command_line {
+            return format!(":{}", line);
+        }
+        let mut status = self.mode.label().to_string();
+        if !self.pending.is_empty() {
+            status.push_str(&format!("  {}", self.pending));
+        }
+        if !self.message.is_empty() {
+            status.push_str(&format!("  {}", self.message));
+        }
+        status
+    }
+
+    /// 记录插入模式下执行的命令，供 `.` 重复
+    pub fn record(&mut self, command: &EditorCommand) {
+        if self.mode != VimMode::Insert || self.repeating {
+            return;
+        }
+        if let Some(change) = &mut self.last_change {
+            change.inserted.push(command.clone());
+        }
+    }
+
+    /// 处理一次按键，在默认按键绑定之前调用
+    pub fn handle_key(
+        &mut self,
+        editor: &mut Editor,
+        key: &Key,
+        modifiers: Modifiers,
+    ) -> VimOutcome {
+        if self.command_line.is_some() {
+            return self.command_line_key(editor, key);
+        }
+        if self.mode == VimMode::Insert {
+            if *key == Key::Escape {
+                self.escape_insert(editor);
+                return VimOutcome::Handled;
+            }
+            return VimOutcome::Unhandled;
+        }
+        if modifiers.ctrl() || modifiers.meta() {
+            return match key {
+                Key::Character(c) if c == "r" && self.mode == VimMode::Normal => {
+                    editor.execute(EditorCommand::Redo);
+                    self.clamp_cursor(editor);
+                    VimOutcome::Handled
+                }
+                _ => VimOutcome::Unhandled,
+            };
+        }
+        let ch = match key {
+            Key::Character(c) => {
+                for ch in c.chars() {
+                    self.key_char(editor, ch);
+                }
+                return VimOutcome::Handled;
+            }
+            Key::Escape => {
+                self.pending.clear();
+                self.message.clear();
+                if self.mode != VimMode::Normal {
+                    self.exit_visual(editor);
+                }
+                editor.execute(EditorCommand::ClearSecondaryCursors);
+                return VimOutcome::Handled;
+            }
+            Key::PageUp | Key::PageDown => return VimOutcome::Unhandled,
+            Key::ArrowLeft | Key::Backspace => 'h',
+            Key::ArrowRight => 'l',
+            Key::ArrowUp => 'k',
+            Key::ArrowDown | Key::Enter => 'j',
+            Key::Home => '0',
+            Key::End => '$',
+            _ => return VimOutcome::Handled,
+        };
+        self.key_char(editor, ch);
+        VimOutcome::Handled
+    }
+
+    fn key_char(&mut self, editor: &mut Editor, ch: char) {
+        self.message.clear();
+        let visual = matches!(self.mode, VimMode::Visual | VimMode::VisualLine);
+        if visual && self.pending.chars().all(|c| c.is_ascii_digit()) {
+            if let Some(op) = Operator::from_visual_key(ch) {
+                self.pending.clear();
+                self.visual_operate(editor, op);
+                return;
+            }
+            if ch == 'o' {
+                self.pending.clear();
+                self.visual_keys.push('o');
+                self.update_visual_selection(editor);
+                return;
+            }
+        }
+        self.pending.push(ch);
+        match parse_normal(&self.pending) {
+            Parsed::Incomplete => {}
+            Parsed::Invalid => self.pending.clear(),
+            Parsed::Complete((count, command)) => {
+                let keys = std::mem::take(&mut self.pending);
+                if visual {
+                    self.run_visual(editor, command, keys);
+                } else {
+                    self.run_normal(editor, count, command, keys);
+                }
+            }
+        }
+    }
+
+    fn run_normal(
+        &mut self,
+        editor: &mut Editor,
+        count: Option<usize>,
+        command: NormalCommand,
+        keys: String,
+    ) {
+        let rope = editor.rope();
+        let pos = editor.get_cursor_position();
+        let n = count.unwrap_or(1);
+        match command {
+            NormalCommand::Move(_) => {
+                editor.execute(EditorCommand::VimMotion(keys));
+            }
+            NormalCommand::Operate(op, motion) => {
+                let Some((range, linewise)) = operator_range_at(&rope, pos, op, motion, count)
+                else {
+                    return;
+                };
+                if op != Operator::Yank {
+                    self.begin_change(keys.clone());
+                    self.begin_undo(editor);
+                }
+                self.apply_operator(editor, &rope, op, range, linewise, keys);
+            }
+            NormalCommand::DeleteChar => {
+                let range = delete_char_range(&rope, pos, n);
+                if !range.is_empty() {
+                    self.begin_change(keys.clone());
+                    self.begin_undo(editor);
+                    self.apply_operator(editor, &rope, Operator::Delete, range, false, keys);
+                }
+            }
+            NormalCommand::Put { before } => {
+                self.begin_change(keys);
+                self.begin_undo(editor);
+                self.put(editor, &rope, pos, before, n);
+            }
+            NormalCommand::Insert(at) => {
+                self.begin_change(keys);
+                self.begin_undo(editor);
+                self.enter_insert(editor, &rope, pos, at);
+            }
+            NormalCommand::Undo => {
+                for _ in 0..n {
+                    editor.execute(EditorCommand::Undo);
+                }
+            }
+            NormalCommand::Repeat => self.repeat(editor, count),
+            NormalCommand::Visual | NormalCommand::VisualLine => {
+                self.mode = if command == NormalCommand::Visual {
+                    VimMode::Visual
+                } else {
+                    VimMode::VisualLine
+                };
+                self.visual_anchor = pos;
+                self.visual_keys = keys;
+                self.update_visual_selection(editor);
+            }
+            NormalCommand::CommandLine => self.command_line = Some(String::new()),
+        }
+        if self.mode == VimMode::Normal {
+            self.clamp_cursor(editor);
+            self.end_undo(editor);
+        }
+    }
+
+    /// 可视模式下只执行移动和模式切换，按键追加到 visual_keys
+    fn run_visual(&mut self, editor: &mut Editor, command: NormalCommand, keys: String) {
+        match command {
+            NormalCommand::Move(_) => {}
+            NormalCommand::Visual | NormalCommand::VisualLine => {
+                let mode = if command == NormalCommand::Visual {
+                    VimMode::Visual
+                } else {
+                    VimMode::VisualLine
+                };
+                if self.mode == mode {
+                    self.exit_visual(editor);
+                    return;
+                }
+                self.mode = mode;
+            }
+            _ => return,
+        }
+        self.visual_keys.push_str(&keys);
+        self.update_visual_selection(editor);
+    }
+
+    /// 显示可视模式的选区：直接设置而不执行命令，宏中只录入最终的相对操作
+    fn update_visual_selection(&self, editor: &mut Editor) {
+        let rope = editor.rope();
+        if let Some(state) = visual_state(&rope, self.visual_anchor, &self.visual_keys) {
+            let (range, _) = visual_range(&rope, &state);
+            editor.set_cursor_position(char_to_cursor(&rope, range.start));
+            editor.select_to(char_to_cursor(&rope, range.end));
+        }
+    }
+
+    fn exit_visual(&mut self, editor: &mut Editor) {
+        self.mode = VimMode::Normal;
+        let keys = std::mem::take(&mut self.visual_keys);
+        editor.set_cursor_position(self.visual_anchor);
+        editor.execute(EditorCommand::VimMotion(keys));
+        self.clamp_cursor(editor);
+    }
+
+    fn visual_operate(&mut self, editor: &mut Editor, op: Operator) {
+        let rope = editor.rope();
+        let mut keys = std::mem::take(&mut self.visual_keys);
+        keys.push(op.key());
+        let Some(state) = visual_state(&rope, self.visual_anchor, &keys) else {
+            return;
+        };
+        let (range, linewise) = visual_range(&rope, &state);
+        self.mode = VimMode::Normal;
+        if op == Operator::Change {
+            // 可视模式的修改不支持 `.` 重复
+            self.last_change = None;
+        }
+        editor.set_cursor_position(self.visual_anchor);
+        if op != Operator::Yank {
+            self.begin_undo(editor);
+        }
+        self.apply_operator(editor, &rope, op, range, linewise, keys);
+        if self.mode == VimMode::Normal {
+            self.clamp_cursor(editor);
+            self.end_undo(editor);
+        }
+    }
+
+    fn begin_change(&mut self, keys: String) {
+        if !self.repeating {
+            self.last_change = Some(Change {
+                keys,
+                inserted: Vec::new(),
+            });
+        }
+    }
+
+    /// 一次修改(包括随后在插入模式下的输入)作为一次撤销，回到普通模式时结束
+    fn begin_undo(&mut self, editor: &mut Editor) {
+        if !self.undo_group {
+            editor.begin_undo_group();
+            self.undo_group = true;
+        }
+    }
+
+    fn end_undo(&mut self, editor: &mut Editor) {
+        if std::mem::take(&mut self.undo_group) {
+            editor.end_undo_group();
+        }
+    }
+
+    /// 切换到其他按键方案时回到普通模式，结束未完成的修改
+    pub fn reset(&mut self, editor: &mut Editor) {
+        self.end_undo(editor);
+        self.mode = VimMode::Normal;
+        self.pending.clear();
+        self.command_line = None;
+        self.visual_keys.clear();
+    }
+
+    /// 对区间执行操作符，被删除或复制的文本存入寄存器。
+    /// keys 是从当前光标选中同一区间的按键，以 `VimSelect` 执行
+    fn apply_operator(
+        &mut self,
+        editor: &mut Editor,
+        rope: &Rope,
+        op: Operator,
+        range: Range<usize>,
+        linewise: bool,
+        keys: String,
+    ) {
+        let mut text = rope.slice(range.clone()).to_string();
+        if linewise && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        let start = char_to_cursor(rope, range.start);
+        let selection = operator_selection(rope, op, range.clone(), linewise);
+        match op {
+            Operator::Yank => {
+                // 选中后向左移动，收起到区间开头
+                if !linewise && start != editor.get_cursor_position() {
+                    editor.execute(EditorCommand::VimSelect(keys));
+                    editor.execute(EditorCommand::Move {
+                        motion: Motion::Left,
+                        select: false,
+                    });
+                }
+            }
+            Operator::Delete => {
+                if !selection.is_empty() {
+                    editor.execute(EditorCommand::VimSelect(keys));
+                    editor.execute(EditorCommand::Cut);
+                }
+                if linewise {
+                    editor.execute(EditorCommand::VimMotion("^".to_string()));
+                }
+            }
+            Operator::Change => {
+                if !selection.is_empty() {
+                    editor.execute(EditorCommand::VimSelect(keys));
+                    if linewise {
+                        // 保留一个带原缩进的空行
+                        let indent = first_non_blank(rope, start.line).col;
+                        let indent = rope.slice(range.start..range.start + indent).to_string();
+                        editor.execute(EditorCommand::Paste(indent));
+                    } else {
+                        editor.execute(EditorCommand::Cut);
+                    }
+                }
+                self.mode = VimMode::Insert;
+            }
+        }
+        self.register = Register { text, linewise };
+    }
+
+    /// p / P：在光标之后或之前放入寄存器内容 count 次
+    fn put(&mut self, editor: &mut Editor, rope: &Rope, pos: Position, before: bool, count: usize) {
+        if self.register.text.is_empty() {
+            return;
+        }
+        let text = self.register.text.repeat(count);
+        if self.register.linewise {
+            let lines = text.matches('\n').count();
+            if before {
+                editor.execute(EditorCommand::VimMotion("0".to_string()));
+                editor.execute(EditorCommand::Paste(text));
+                editor.execute(EditorCommand::VimMotion(format!("{}k", lines)));
+            } else {
+                // 从行尾插入，最后一行没有换行符时也能放到它的下面
+                editor.execute(EditorCommand::Move {
+                    motion: Motion::LineEnd,
+                    select: false,
+                });
+                editor.execute(EditorCommand::Paste(format!(
+                    "\n{}",
+                    text.trim_end_matches('\n')
+                )));
+                if lines > 1 {
+                    editor.execute(EditorCommand::VimMotion(format!("{}k", lines - 1)));
+                }
+            }
+            editor.execute(EditorCommand::VimMotion("^".to_string()));
+        } else {
+            if !before && line_len(rope, pos.line) > 0 {
+                editor.execute(EditorCommand::VimMotion("l".to_string()));
+            }
+            editor.execute(EditorCommand::Paste(text));
+            editor.execute(EditorCommand::Move {
+                motion: Motion::Left,
+                select: false,
+            });
+        }
+    }
+
+    fn enter_insert(&mut self, editor: &mut Editor, rope: &Rope, pos: Position, at: InsertAt) {
+        let line_end = EditorCommand::Move {
+            motion: Motion::LineEnd,
+            select: false,
+        };
+        match at {
+            InsertAt::Before => {}
+            InsertAt::After => {
+                editor.execute(EditorCommand::VimMotion("l".to_string()));
+            }
+            InsertAt::LineStart => {
+                editor.execute(EditorCommand::VimMotion("^".to_string()));
+            }
+            InsertAt::LineEnd => {
+                editor.execute(line_end);
+            }
+            InsertAt::LineBelow => {
+                editor.execute(line_end);
+                editor.execute(EditorCommand::Newline);
+            }
+            InsertAt::LineAbove => {
+                let indent = first_non_blank(rope, pos.line).col;
+                let start = rope.line_to_char(pos.line);
+                let indent = rope.slice(start..start + indent).to_string();
+                editor.execute(EditorCommand::VimMotion("0".to_string()));
+                editor.execute(EditorCommand::Paste(format!("{}\n", indent)));
+                editor.execute(EditorCommand::Move {
+                    motion: Motion::Up,
+                    select: false,
+                });
+                editor.execute(line_end);
+            }
+        }
+        self.mode = VimMode::Insert;
+    }
+
+    fn escape_insert(&mut self, editor: &mut Editor) {
+        self.mode = VimMode::Normal;
+        if editor.get_cursor_position().col > 0 {
+            editor.execute(EditorCommand::VimMotion("h".to_string()));
+        }
+        self.clamp_cursor(editor);
+        self.end_undo(editor);
+    }
+
+    /// `.`：重放最近一次修改，给出计数时替换原来的计数
+    fn repeat(&mut self, editor: &mut Editor, count: Option<usize>) {
+        let Some(change) = self.last_change.clone() else {
+            return;
+        };
+        let keys = match count {
+            Some(count) => format!(
+                "{}{}",
+                count,
+                change.keys.trim_start_matches(|c: char| c.is_ascii_digit())
+            ),
+            None => change.keys,
+        };
+        self.repeating = true;
+        for ch in keys.chars() {
+            self.key_char(editor, ch);
+        }
+        if self.mode == VimMode::Insert {
+            for command in change.inserted {
+                editor.execute(command);
+            }
+            self.escape_insert(editor);
+        }
+        self.repeating = false;
+    }
+
+    /// 普通模式下没有选区，光标停在字符上，不能位于非空行的行尾
+    fn clamp_cursor(&self, editor: &mut Editor) {
+        if !editor.get_selection().is_empty() {
+            // 撤销可能恢复操作前的选区，收起到它的开头
+            editor.execute(EditorCommand::Move {
+                motion: Motion::Left,
+                select: false,
+            });
+        }
+        let rope = editor.rope();
+        let pos = editor.get_cursor_position();
+        if clamp_to_char(&rope, pos) != pos {
+            editor.execute(EditorCommand::VimMotion("h".to_string()));
+        }
+    }
+
+    fn command_line_key(&mut self, editor: &mut Editor, key: &Key) -> VimOutcome {
+        let Some(line) = &mut self.command_line else {
+            return VimOutcome::Unhandled;
+        };
+        match key {
+            Key::Character(c) => line.push_str(c),
+            Key::Backspace if line.is_empty() => self.command_line = None,
+            Key::Backspace => {
+                line.pop();
+            }
+            Key::Escape => self.command_line = None,
+            Key::Enter => {
+                let line = self.command_line.take().unwrap_or_default();
+                return self.run_ex(editor, line.trim());
+            }
+            _ => {}
+        }
+        VimOutcome::Handled
+    }
+
+    /// 执行 `:` 命令：`w [文件]`、`q`、`q!`、`wq`/`x` 以及跳转到行号；
+    /// 有未保存的修改时 `q` 不退出
+    fn run_ex(&mut self, editor: &mut Editor, line: &str) -> VimOutcome {
+        let (name, arg) = match line.split_once(' ') {
+            Some((name, arg)) => (name, Some(PathBuf::from(arg.trim()))),
+            None => (line, None),
+        };
+        match name {
+            "w" | "wq" | "x" => match editor.save(arg) {
+                Ok(path) => {
+                    self.message = format!("已写入 {}", path.display());
+                    if name != "w" {
+                        return VimOutcome::Quit;
+                    }
+                }
+                Err(err) => self.message = format!("写入失败：{}", err),
+            },
+            "q" if editor.is_modified() => {
+                self.message = "未保存的修改，用 :q! 放弃修改并退出".to_string();
+            }
+            "q" | "q!" => return VimOutcome::Quit,
+            _ => match name.parse::<usize>() {
+                Ok(number) => {
+                    editor.execute(EditorCommand::VimMotion(format!("{}G", number.max(1))));
+                }
+                Err(_) => self.message = format!("不是编辑器命令：{}", line),
+            },
+        }
+        VimOutcome::Handled
+    }
+}
+
+/// Vim 方案录入宏的命令：按键都从主光标处解释，回放时相对光标生效
+impl Editor {
+    /// 按 Vim 的动作按键(如 `3w`、`f,`)或可视模式按键序列(到达选区的活动端)移动主光标
+    pub(crate) fn vim_motion(&mut self, keys: &str) {
+        let rope = self.rope();
+        let pos = self.get_cursor_position();
+        let target = if keys.starts_with(['v', 'V']) {
+            visual_state(&rope, pos, keys).map(|state| state.head)
+        } else {
+            let (count, motion) = split_count(keys);
+            match parse_motion(motion) {
+                Parsed::Complete(motion) => motion_target(&rope, pos, motion, count),
+                _ => None,
+            }
+        };
+        if let Some(target) = target {
+            self.set_cursor_position(target);
+        }
+    }
+
+    /// 选中 Vim 操作符按键(如 `d2w`、`cc`、`3x`)或可视模式按键序列加操作符作用的区间
+    pub(crate) fn vim_select(&mut self, keys: &str) {
+        let rope = self.rope();
+        let pos = self.get_cursor_position();
+        let selected = if keys.starts_with(['v', 'V']) {
+            visual_state(&rope, pos, keys).and_then(|state| {
+                let (range, linewise) = visual_range(&rope, &state);
+                Some((state.op?, range, linewise))
+            })
+        } else {
+            match parse_normal(keys) {
+                Parsed::Complete((count, NormalCommand::Operate(op, motion))) => {
+                    operator_range_at(&rope, pos, op, motion, count)
+                        .map(|(range, linewise)| (op, range, linewise))
+                }
+                Parsed::Complete((count, NormalCommand::DeleteChar)) => Some((
+                    Operator::Delete,
+                    delete_char_range(&rope, pos, count.unwrap_or(1)),
+                    false,
+                )),
+                _ => None,
+            }
+        };
+        let Some((op, range, linewise)) = selected else {
+            return;
+        };
+        let range = operator_selection(&rope, op, range, linewise);
+        self.set_cursor_position(char_to_cursor(&rope, range.start));
+        self.select_to(char_to_cursor(&rope, range.end));
+    }
+}
+
+/// 拆分出开头的计数(不以 0 开头)
+fn split_count(keys: &str) -> (Option<usize>, &str) {
+    if keys.starts_with('0') {
+        return (None, keys);
+    }
+    let digits = keys.chars().take_while(|c| c.is_ascii_digit()).count();
+    (keys[..digits].parse().ok(), &keys[digits..])
+}
+
+fn multiply(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    match (a, b) {
+        (None, None) => None,
+        _ => Some(a.unwrap_or(1) * b.unwrap_or(1)),
+    }
+}
+
+/// 解析普通模式的按键序列：[计数] 命令，或 [计数] 操作符 [计数] 动作
+fn parse_normal(keys: &str) -> Parsed<(Option<usize>, NormalCommand)> {
+    let (count, rest) = split_count(keys);
+    let Some(first) = rest.chars().next() else {
+        return Parsed::Incomplete;
+    };
+    let command = match first {
+        'd' | 'c' | 'y' => {
+            let op = match first {
+                'd' => Operator::Delete,
+                'c' => Operator::Change,
+                _ => Operator::Yank,
+            };
+            let (motion_count, motion) = split_count(&rest[1..]);
+            let count = multiply(count, motion_count);
+            if motion.starts_with(first) {
+                return match motion.len() {
+                    1 => Parsed::Complete((count, NormalCommand::Operate(op, None))),
+                    _ => Parsed::Invalid,
+                };
+            }
+            return match parse_motion(motion) {
+                Parsed::Complete(motion) => {
+                    Parsed::Complete((count, NormalCommand::Operate(op, Some(motion))))
+                }
+                Parsed::Incomplete => Parsed::Incomplete,
+                Parsed::Invalid => Parsed::Invalid,
+            };
+        }
+        'D' => NormalCommand::Operate(Operator::Delete, Some(VimMotion::LineEnd)),
+        'C' => NormalCommand::Operate(Operator::Change, Some(VimMotion::LineEnd)),
+        'S' => NormalCommand::Operate(Operator::Change, None),
+        'x' => NormalCommand::DeleteChar,
+        'p' => NormalCommand::Put { before: false },
+        'P' => NormalCommand::Put { before: true },
+        'i' => NormalCommand::Insert(InsertAt::Before),
+        'a' => NormalCommand::Insert(InsertAt::After),
+        'I' => NormalCommand::Insert(InsertAt::LineStart),
+        'A' => NormalCommand::Insert(InsertAt::LineEnd),
+        'o' => NormalCommand::Insert(InsertAt::LineBelow),
+        'O' => NormalCommand::Insert(InsertAt::LineAbove),
+        'u' => NormalCommand::Undo,
+        '.' => NormalCommand::Repeat,
+        'v' => NormalCommand::Visual,
+        'V' => NormalCommand::VisualLine,
+        ':' => NormalCommand::CommandLine,
+        _ => {
+            return match parse_motion(rest) {
+                Parsed::Complete(motion) => Parsed::Complete((count, NormalCommand::Move(motion))),
+                Parsed::Incomplete => Parsed::Incomplete,
+                Parsed::Invalid => Parsed::Invalid,
+            }
+        }
+    };
+    if rest.chars().count() == 1 {
+        Parsed::Complete((count, command))
+    } else {
+        Parsed::Invalid
+    }
+}
+
+fn parse_motion(keys: &str) -> Parsed<VimMotion> {
+    let mut chars = keys.chars();
+    let Some(first) = chars.next() else {
+        return Parsed::Incomplete;
+    };
+    let second = chars.next();
+    if chars.next().is_some() {
+        return Parsed::Invalid;
+    }
+    let motion = match (first, second) {
+        ('h', None) => VimMotion::Left,
+        ('l', None) => VimMotion::Right,
+        ('j', None) => VimMotion::Down,
+        ('k', None) => VimMotion::Up,
+        ('w', None) => VimMotion::WordStart,
+        ('b', None) => VimMotion::WordBack,
+        ('e', None) => VimMotion::WordEnd,
+        ('0', None) => VimMotion::LineStart,
+        ('^', None) => VimMotion::FirstNonBlank,
+        ('$', None) => VimMotion::LineEnd,
+        ('G', None) => VimMotion::LastLine,
+        ('g' | 'f' | 't' | 'F' | 'T', None) => return Parsed::Incomplete,
+        ('g', Some('g')) => VimMotion::FirstLine,
+        ('f', Some(c)) => VimMotion::FindForward(c),
+        ('t', Some(c)) => VimMotion::TillForward(c),
+        ('F', Some(c)) => VimMotion::FindBackward(c),
+        ('T', Some(c)) => VimMotion::TillBackward(c),
+        _ => return Parsed::Invalid,
+    };
+    Parsed::Complete(motion)
+}
+
+/// 从 pos 出发执行 count 次动作后的位置，动作无法执行(如 f 找不到字符)时返回 None
+fn motion_target(
+    rope: &Rope,
+    pos: Position,
+    motion: VimMotion,
+    count: Option<usize>,
+) -> Option<Position> {
+    let n = count.unwrap_or(1);
+    let idx = pos.char_offset(rope);
+    let target = match motion {
+        VimMotion::Left => Position::new(pos.line, pos.col.saturating_sub(n)),
+        VimMotion::Right => Position::new(pos.line, (pos.col + n).min(line_len(rope, pos.line))),
+        VimMotion::Down | VimMotion::Up => {
+            let line = if motion == VimMotion::Down {
+                (pos.line + n).min(last_line(rope))
+            } else {
+                pos.line.saturating_sub(n)
+            };
+            Position::new(line, pos.col.min(line_len(rope, line)))
+        }
+        VimMotion::WordStart => {
+            char_to_cursor(rope, (0..n).fold(idx, |i, _| next_word_start(rope, i)))
+        }
+        VimMotion::WordBack => {
+            char_to_cursor(rope, (0..n).fold(idx, |i, _| prev_word_start(rope, i)))
+        }
+        VimMotion::WordEnd => char_to_cursor(rope, (0..n).fold(idx, |i, _| word_end(rope, i))),
+        VimMotion::LineStart => Position::new(pos.line, 0),
+        VimMotion::FirstNonBlank => first_non_blank(rope, pos.line),
+        VimMotion::LineEnd => {
+            let line = (pos.line + n - 1).min(last_line(rope));
+            Position::new(line, line_len(rope, line).saturating_sub(1))
+        }
+        VimMotion::FirstLine | VimMotion::LastLine => {
+            let line = match count {
+                Some(count) => count.saturating_sub(1),
+                None if motion == VimMotion::FirstLine => 0,
+                None => last_line(rope),
+            };
+            first_non_blank(rope, line.min(last_line(rope)))
+        }
+        VimMotion::FindForward(ch) | VimMotion::TillForward(ch) => {
+            let line: Vec<char> = rope.line(pos.line).chars().collect();
+            let col = (pos.col + 1..line.len())
+                .filter(|col| line[*col] == ch)
+                .nth(n - 1)?;
+            match motion {
+                VimMotion::TillForward(_) => Position::new(pos.line, col - 1),
+                _ => Position::new(pos.line, col),
+            }
+        }
+        VimMotion::FindBackward(ch) | VimMotion::TillBackward(ch) => {
+            let line: Vec<char> = rope.line(pos.line).chars().collect();
+            let col = (0..pos.col.min(line.len()))
+                .rev()
+                .filter(|col| line[*col] == ch)
+                .nth(n - 1)?;
+            match motion {
+                VimMotion::TillBackward(_) => Position::new(pos.line, col + 1),
+                _ => Position::new(pos.line, col),
+            }
+        }
+    };
+    Some(target)
+}
+
+/// 动作从 from 到 to 所覆盖的字符区间，以及是否按整行处理
+fn operator_range(
+    rope: &Rope,
+    from: Position,
+    to: Position,
+    kind: MotionKind,
+) -> (Range<usize>, bool) {
+    let (start, end) = if from <= to { (from, to) } else { (to, from) };
+    match kind {
+        MotionKind::Linewise => (line_range(rope, start.line, end.line), true),
+        MotionKind::Exclusive => (start.char_offset(rope)..end.char_offset(rope), false),
+        MotionKind::Inclusive => {
+            let end = (end.char_offset(rope) + 1).min(line_end(rope, end.line));
+            (start.char_offset(rope)..end, false)
+        }
+    }
+}
+
+/// Vim 的单词分类：换行视为空白，中日韩文字视为单词字符
+fn vim_class(ch: char) -> CharClass {
+    match CharClass::of(ch) {
+        CharClass::LineBreak => CharClass::Whitespace,
+        CharClass::Cjk => CharClass::Word,
+        class => class,
+    }
+}
+
+/// w：下一个单词的开头
+fn next_word_start(rope: &Rope, idx: usize) -> usize {
+    let len = rope.len_chars();
+    let mut idx = idx;
+    if idx >= len {
+        return len;
+    }
+    let class = vim_class(rope.char(idx));
+    if class != CharClass::Whitespace {
+        while idx < len && vim_class(rope.char(idx)) == class {
+            idx += 1;
+        }
+    }
+    while idx < len && vim_class(rope.char(idx)) == CharClass::Whitespace {
+        idx += 1;
+    }
+    idx
+}
+
+/// b：上一个单词的开头
+fn prev_word_start(rope: &Rope, idx: usize) -> usize {
+    let mut idx = idx.min(rope.len_chars());
+    while idx > 0 && vim_class(rope.char(idx - 1)) == CharClass::Whitespace {
+        idx -= 1;
+    }
+    if idx == 0 {
+        return 0;
+    }
+    let class = vim_class(rope.char(idx - 1));
+    while idx > 0 && vim_class(rope.char(idx - 1)) == class {
+        idx -= 1;
+    }
+    idx
+}
+
+/// e：当前或下一个单词的最后一个字符，之后没有单词时不移动
+fn word_end(rope: &Rope, start: usize) -> usize {
+    let len = rope.len_chars();
+    let mut idx = start + 1;
+    while idx < len && vim_class(rope.char(idx)) == CharClass::Whitespace {
+        idx += 1;
+    }
+    if idx >= len {
+        return start.min(len.saturating_sub(1));
+    }
+    let class = vim_class(rope.char(idx));
+    while idx + 1 < len && vim_class(rope.char(idx + 1)) == class {
+        idx += 1;
+    }
+    idx
+}
+
+/// first 到 last 行(含换行符)的字符区间
+fn line_range(rope: &Rope, first: usize, last: usize) -> Range<usize> {
+    let end = if last + 1 < rope.len_lines() {
+        rope.line_to_char(last + 1)
+    } else {
+        rope.len_chars()
+    };
+    rope.line_to_char(first)..end
+}
+
+fn first_non_blank(rope: &Rope, line: usize) -> Position {
+    let indent = rope
+        .line(line)
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .count();
+    Position::new(line, indent.min(line_len(rope, line)))
+}
+
+/// 光标停在字符上：非空行中不超过最后一个字符
+fn clamp_to_char(rope: &Rope, pos: Position) -> Position {
+    let line = pos.line.min(last_line(rope));
+    Position::new(line, pos.col.min(line_len(rope, line).saturating_sub(1)))
+}
+
+/// 可视模式的按键序列执行后的状态
+struct VisualState {
+    mode: VimMode,
+    anchor: Position,
+    head: Position,
+    /// 结束序列的操作符
+    op: Option<Operator>,
+}
+
+/// 从 anchor 出发依次执行可视模式的按键序列(以 `v` 或 `V` 开头)，遇到操作符时结束
+fn visual_state(rope: &Rope, anchor: Position, keys: &str) -> Option<VisualState> {
+    let mut chars = keys.chars();
+    let mode = match chars.next()? {
+        'v' => VimMode::Visual,
+        'V' => VimMode::VisualLine,
+        _ => return None,
+    };
+    let mut state = VisualState {
+        mode,
+        anchor,
+        head: anchor,
+        op: None,
+    };
+    let mut pending = String::new();
+    for ch in chars {
+        if pending.chars().all(|c| c.is_ascii_digit()) {
+            if let Some(op) = Operator::from_visual_key(ch) {
+                state.op = Some(op);
+                break;
+            }
+            if ch == 'o' {
+                pending.clear();
+                std::mem::swap(&mut state.anchor, &mut state.head);
+                continue;
+            }
+        }
+        pending.push(ch);
+        match parse_normal(&pending) {
+            Parsed::Incomplete => {}
+            Parsed::Invalid => pending.clear(),
+            Parsed::Complete((count, command)) => {
+                pending.clear();
+                match command {
+                    NormalCommand::Move(motion) => {
+                        if let Some(target) = motion_target(rope, state.head, motion, count) {
+                            state.head = clamp_to_char(rope, target);
+                        }
+                    }
+                    NormalCommand::Visual => state.mode = VimMode::Visual,
+                    NormalCommand::VisualLine => state.mode = VimMode::VisualLine,
+                    _ => {}
+                }
+            }
+        }
+    }
+    Some(state)
+}
+
+/// 可视模式选中的字符区间和是否按整行处理
+fn visual_range(rope: &Rope, state: &VisualState) -> (Range<usize>, bool) {
+    let (start, end) = if state.anchor <= state.head {
+        (state.anchor, state.head)
+    } else {
+        (state.head, state.anchor)
+    };
+    if state.mode == VimMode::VisualLine {
+        return (line_range(rope, start.line, end.line), true);
+    }
+    let end = (end.char_offset(rope) + 1).min(rope.len_chars());
+    (start.char_offset(rope)..end, false)
+}
+
+/// 操作符从 pos 作用于动作(None 表示整行)的字符区间和是否按整行处理，动作无法执行时返回 None
+fn operator_range_at(
+    rope: &Rope,
+    pos: Position,
+    op: Operator,
+    motion: Option<VimMotion>,
+    count: Option<usize>,
+) -> Option<(Range<usize>, bool)> {
+    let Some(motion) = motion else {
+        let last = (pos.line + count.unwrap_or(1) - 1).min(last_line(rope));
+        return Some((line_range(rope, pos.line, last), true));
+    };
+    // cw 在单词上时与 ce 相同
+    let on_word = rope
+        .get_char(pos.char_offset(rope))
+        .is_some_and(|c| vim_class(c) != CharClass::Whitespace);
+    let motion = match motion {
+        VimMotion::WordStart if op == Operator::Change && on_word => VimMotion::WordEnd,
+        _ => motion,
+    };
+    let mut target = motion_target(rope, pos, motion, count)?;
+    // dw 不跨越到下一行
+    if motion == VimMotion::WordStart && target.line > pos.line {
+        target = Position::new(pos.line, line_len(rope, pos.line));
+    }
+    Some(operator_range(rope, pos, target, motion.kind()))
+}
+
+/// x：从 pos 起最多 count 个字符，不包括换行符
+fn delete_char_range(rope: &Rope, pos: Position, count: usize) -> Range<usize> {
+    let start = pos.char_offset(rope);
+    start..(start + count).min(line_end(rope, pos.line)).max(start)
+}
+
+/// 操作符实际选中的区间：删除末尾的行时连同前一行的换行符，修改整行时保留换行符
+fn operator_selection(
+    rope: &Rope,
+    op: Operator,
+    range: Range<usize>,
+    linewise: bool,
+) -> Range<usize> {
+    if !linewise || range.is_empty() {
+        return range;
+    }
+    let ends_with_newline = rope.char(range.end - 1) == '\n';
+    match op {
+        Operator::Delete if range.end == rope.len_chars() && !ends_with_newline => {
+            range.start.saturating_sub(1)..range.end
+        }
+        Operator::Change if ends_with_newline => range.start..range.end - 1,
+        _ => range,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 按下按键序列，`\x1b` 为 Esc，`\n` 为回车；插入模式下未处理的按键作为输入
+    fn press(vim: &mut Vim, editor: &mut Editor, keys: &str) {
+        for ch in keys.chars() {
+            let key = match ch {
+                '\x1b' => Key::Escape,
+                '\n' => Key::Enter,
+                _ => Key::Character(ch.to_string()),
+            };
+            if vim.handle_key(editor, &key, Modifiers::empty()) == VimOutcome::Unhandled {
+                let command = EditorCommand::TypeChar(ch);
+                vim.record(&command);
+                editor.execute(command);
+            }
+        }
+    }
+
+    fn run(text: &str, keys: &str) -> (Editor, Vim) {
+        let mut editor = Editor::with_text(text);
+        let mut vim = Vim::default();
+        press(&mut vim, &mut editor, keys);
+        (editor, vim)
+    }
+
+    /// 执行后回到普通模式，检查文本和光标
+    fn check(text: &str, keys: &str, expected: &str, cursor: (usize, usize)) {
+        let (editor, vim) = run(text, keys);
+        assert_eq!(editor.get_text(), expected, "{:?}", keys);
+        let pos = editor.get_cursor_position();
+        assert_eq!((pos.line, pos.col), cursor, "{:?}", keys);
+        assert_eq!(vim.mode, VimMode::Normal, "{:?}", keys);
+        assert!(editor.get_selection().is_empty(), "{:?}", keys);
+    }
+
+    #[test]
+    fn parse_counts_operators_and_motions() {
+        use NormalCommand::*;
+        let complete = |count, command| Parsed::Complete((count, command));
+        assert_eq!(
+            parse_normal("3dw"),
+            complete(
+                Some(3),
+                Operate(Operator::Delete, Some(VimMotion::WordStart))
+            )
+        );
+        assert_eq!(
+            parse_normal("2d3w"),
+            complete(
+                Some(6),
+                Operate(Operator::Delete, Some(VimMotion::WordStart))
+            )
+        );
+        assert_eq!(
+            parse_normal("dfx"),
+            complete(
+                None,
+                Operate(Operator::Delete, Some(VimMotion::FindForward('x')))
+            )
+        );
+        assert_eq!(
+            parse_normal("yy"),
+            complete(None, Operate(Operator::Yank, None))
+        );
+        assert_eq!(
+            parse_normal("10j"),
+            complete(Some(10), Move(VimMotion::Down))
+        );
+        assert_eq!(
+            parse_normal("0"),
+            complete(None, Move(VimMotion::LineStart))
+        );
+        assert_eq!(
+            parse_normal("gg"),
+            complete(None, Move(VimMotion::FirstLine))
+        );
+        assert_eq!(parse_normal("x"), complete(None, DeleteChar));
+        for keys in ["", "3", "d", "d2", "df", "g", "cf"] {
+            assert_eq!(parse_normal(keys), Parsed::Incomplete, "{:?}", keys);
+        }
+        for keys in ["dq", "dyy", "gx", "q"] {
+            assert_eq!(parse_normal(keys), Parsed::Invalid, "{:?}", keys);
+        }
+    }
+
+    #[test]
+    fn motion_targets() {
+        let rope = Rope::from_str("foo bar\n  baz qux\n");
+        let target = |pos: (usize, usize), keys: &str| {
+            let (count, motion) = split_count(keys);
+            let Parsed::Complete(motion) = parse_motion(motion) else {
+                panic!("{:?}", keys);
+            };
+            motion_target(&rope, Position::new(pos.0, pos.1), motion, count)
+                .map(|pos| (pos.line, pos.col))
+        };
+        assert_eq!(target((0, 0), "w"), Some((0, 4)));
+        assert_eq!(target((0, 0), "2w"), Some((1, 2)));
+        assert_eq!(target((1, 2), "b"), Some((0, 4)));
+        assert_eq!(target((0, 0), "e"), Some((0, 2)));
+        assert_eq!(target((0, 0), "2e"), Some((0, 6)));
+        assert_eq!(target((0, 0), "$"), Some((0, 6)));
+        assert_eq!(target((1, 6), "^"), Some((1, 2)));
+        assert_eq!(target((1, 6), "0"), Some((1, 0)));
+        assert_eq!(target((0, 6), "j"), Some((1, 6)));
+        assert_eq!(target((0, 2), "5h"), Some((0, 0)));
+        assert_eq!(target((0, 0), "9l"), Some((0, 7)));
+        // 末尾换行之后的空行不算一行
+        assert_eq!(target((0, 0), "G"), Some((1, 2)));
+        assert_eq!(target((0, 0), "9G"), Some((1, 2)));
+        assert_eq!(target((1, 4), "gg"), Some((0, 0)));
+        assert_eq!(target((0, 0), "fa"), Some((0, 5)));
+        assert_eq!(target((0, 0), "ta"), Some((0, 4)));
+        assert_eq!(target((0, 6), "Fo"), Some((0, 2)));
+        assert_eq!(target((0, 6), "2Fo"), Some((0, 1)));
+        assert_eq!(target((0, 6), "To"), Some((0, 3)));
+        assert_eq!(target((0, 0), "fz"), None);
+    }
+
+    #[test]
+    fn word_ends() {
+        let rope = Rope::from_str("ab  cd,ef 中文\n");
+        assert_eq!(word_end(&rope, 0), 1);
+        assert_eq!(word_end(&rope, 1), 5);
+        assert_eq!(word_end(&rope, 5), 6);
+        assert_eq!(word_end(&rope, 6), 8);
+        assert_eq!(word_end(&rope, 8), 11);
+        // 之后没有单词时不移动
+        assert_eq!(word_end(&rope, 11), 11);
+        assert_eq!(word_end(&Rope::from_str("ab  "), 1), 1);
+    }
+
+    #[test]
+    fn operators_with_motions() {
+        check("one two three", "dw", "two three", (0, 0));
+        check("one two three", "2dw", "three", (0, 0));
+        check("one two three", "d2w", "three", (0, 0));
+        // dw 不跨越到下一行
+        check("one two\nthree", "wdw", "one \nthree", (0, 3));
+        check("one two", "wD", "one ", (0, 3));
+        check("one,two", "dt,", ",two", (0, 0));
+        check("one,two", "df,", "two", (0, 0));
+        check("one two", "$db", "one o", (0, 4));
+        check("a\nb\nc\nd", "j2dd", "a\nd", (1, 0));
+        check("a\nb\nc", "jdj", "a", (0, 0));
+        // 删除末尾的行时连同前一行的换行符
+        check("a\n  b", "jdd", "a", (0, 0));
+        check("a\n  b\nc", "dd", "  b\nc", (0, 2));
+    }
+
+    #[test]
+    fn change_and_delete_char() {
+        // cw 在单词上时与 ce 相同
+        check("one two three", "cwX\x1b", "X two three", (0, 0));
+        check("one two", "wcbX\x1b", "Xtwo", (0, 0));
+        check("  foo\nbar", "ccX\x1b", "  X\nbar", (0, 2));
+        check("abcdef", "l3x", "aef", (0, 1));
+        check("abc", "$x", "ab", (0, 1));
+        check("ab\ncd", "5x", "\ncd", (0, 0));
+        check("  abc", "SX\x1b", "  X", (0, 2));
+    }
+
+    #[test]
+    fn insert_commands() {
+        check("ab\ncd", "Ax\x1bjIy\x1b", "abx\nycd", (1, 0));
+        check("ab", "ax\x1b", "axb", (0, 1));
+        check("ab\ncd", "ox\x1b", "ab\nx\ncd", (1, 0));
+        check("  ab", "Ox\x1b", "  x\n  ab", (0, 2));
+    }
+
+    #[test]
+    fn yank_and_put() {
+        check("one\ntwo", "yyjp", "one\ntwo\none", (2, 0));
+        check("one\ntwo", "jyykP", "two\none\ntwo", (0, 0));
+        check("one\ntwo", "yj2p", "one\none\ntwo\none\ntwo\ntwo", (1, 0));
+        check("abc", "ylp", "aabc", (0, 1));
+        check("one two", "yw$p", "one twoone ", (0, 10));
+        check("one two", "wye0P", "twoone two", (0, 2));
+        check("one two", "dwP", "one two", (0, 3));
+    }
+
+    #[test]
+    fn visual_mode() {
+        check("one two\nthree", "vjd", "hree", (0, 0));
+        check("a\nb\nc", "jVjd", "a", (0, 0));
+        check("one two", "wvey0P", "twoone two", (0, 2));
+        // o 交换选区两端
+        check("abcdef", "lllvlohd", "abf", (0, 2));
+        check("abc", "vlcX\x1b", "Xc", (0, 0));
+        check("abc", "vVd", "", (0, 0));
+        // Esc 回到选区的活动端
+        check("abc", "vll\x1b", "abc", (0, 2));
+        check("abc\ndef", "vjv", "abc\ndef", (1, 0));
+
+        let (editor, vim) = run("ab\ncd", "lvj");
+        assert_eq!(vim.mode, VimMode::Visual);
+        let selection = editor.get_selection();
+        assert_eq!(selection.start(), Position::new(0, 1));
+        assert_eq!(selection.end(), Position::new(1, 2));
+    }
+
+    #[test]
+    fn dot_repeats_last_change() {
+        check("a b c d", "dw.", "c d", (0, 0));
+        check("one two\nthree four", "cwX\x1bj0.", "X two\nX four", (1, 0));
+        // 给出计数时替换原来的计数
+        check("abcdef", "x3.", "ef", (0, 0));
+        check("a\nb", "ox\x1b.", "a\nx\nx\nb", (2, 0));
+        check("abc", "yl.", "abc", (0, 0));
+    }
+
+    #[test]
+    fn each_change_is_one_undo_step() {
+        check("one two", "cwnew\x1bu", "one two", (0, 0));
+        check("one two", "cwa b\x1bu", "one two", (0, 0));
+        check("a\nb\nc", "jddu", "a\nb\nc", (1, 0));
+        check("abcdef", "3xu", "abcdef", (0, 0));
+        check("ab", "ix y\x1bu", "ab", (0, 0));
+        check("a b c", "dw.u", "b c", (0, 0));
+        check("a\nb", "yyp2u", "a\nb", (0, 0));
+
+        let (mut editor, mut vim) = run("one two", "cwnew\x1bu");
+        vim.handle_key(&mut editor, &Key::Character("r".into()), Modifiers::CONTROL);
+        assert_eq!(editor.get_text(), "new two");
+    }
+
+    #[test]
+    fn go_to_line() {
+        check("a\nb\n  c", ":3\n", "a\nb\n  c", (2, 2));
+        check("a\nb", ":0\n", "a\nb", (0, 0));
+        check("a\nb", "j:9\n", "a\nb", (1, 0));
+    }
+
+    fn ex(vim: &mut Vim, editor: &mut Editor, command: &str) -> VimOutcome {
+        press(vim, editor, &format!(":{}", command));
+        vim.handle_key(editor, &Key::Enter, Modifiers::empty())
+    }
+
+    #[test]
+    fn quit_refuses_unsaved_changes() {
+        let (mut editor, mut vim) = run("abc", "");
+        assert_eq!(ex(&mut vim, &mut editor, "q"), VimOutcome::Quit);
+
+        let (mut editor, mut vim) = run("abc", "x");
+        assert_eq!(ex(&mut vim, &mut editor, "q"), VimOutcome::Handled);
+        assert!(vim.message.contains("未保存"));
+        // 撤销回到原来的文本后可以退出
+        press(&mut vim, &mut editor, "u");
+        assert_eq!(ex(&mut vim, &mut editor, "q"), VimOutcome::Quit);
+        press(&mut vim, &mut editor, "x");
+        assert_eq!(ex(&mut vim, &mut editor, "q!"), VimOutcome::Quit);
+    }
+
+    #[test]
+    fn quit_after_write() {
+        let path = std::env::temp_dir().join(format!("editor-x-vim-{}.txt", std::process::id()));
+        let (mut editor, mut vim) = run("abc", "x");
+        let command = format!("w {}", path.display());
+        assert_eq!(ex(&mut vim, &mut editor, &command), VimOutcome::Handled);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "bc");
+        assert_eq!(ex(&mut vim, &mut editor, "q"), VimOutcome::Quit);
+        press(&mut vim, &mut editor, "x");
+        assert_eq!(ex(&mut vim, &mut editor, "wq"), VimOutcome::Quit);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "c");
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,201 @@
+use ropey::Rope;
+use std::ops::Range;
+
+/// 单词边界判断用的字符分类
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CharClass {
+    Whitespace,
+    LineBreak,
+    /// Rust 标识符字符(字母、数字、下划线)
+    Word,
+    /// 中日韩文字
+    Cjk,
+    Punctuation,
+}
+
+impl CharClass {
+    pub fn of(ch: char) -> Self {
+        match ch {
+            '\n' | '\r' => CharClass::LineBreak,
+            _ if ch.is_whitespace() => CharClass::Whitespace,
+            _ if is_cjk(ch) => CharClass::Cjk,
+            _ if ch.is_alphanumeric() || ch == '_' => CharClass::Word,
+            _ => CharClass::Punctuation,
+        }
+    }
+}
+
+fn is_cjk(ch: char) -> bool {
+    matches!(ch as u32,
+        0x3040..=0x30FF     // 平假名、片假名
+        | 0x3400..=0x4DBF   // 扩展 A
+        | 0x4E00..=0x9FFF   // 基本汉字
+        | 0xAC00..=0xD7AF   // 韩文音节
+        | 0xF900..=0xFAFF   // 兼容汉字
+        | 0x20000..=0x2FA1F // 扩展 B 及之后
+    )
+}
+
+/// 下一个单词的结束位置(Ctrl+Right)
+pub fn next_word_boundary(rope: &Rope, char_idx: usize) -> usize {
+    let len = rope.len_chars();
+    let mut idx = char_idx;
+    if idx >= len {
+        return len;
+    }
+    if CharClass::of(rope.char(idx)) == CharClass::LineBreak {
+        return idx + 1;
+    }
+    while idx < len && CharClass::of(rope.char(idx)) == CharClass::Whitespace {
+        idx += 1;
+    }
+    if idx < len {
+        let class = CharClass::of(rope.char(idx));
+        if class != CharClass::LineBreak {
+            while idx < len && CharClass::of(rope.char(idx)) == class {
+                idx += 1;
+            }
+        }
+    }
+    idx
+}
+
+/// 上一个单词的开始位置(Ctrl+Left)
+pub fn prev_word_boundary(rope: &Rope, char_idx: usize) -> usize {
+    let mut idx = char_idx.min(rope.len_chars());
+    if idx == 0 {
+        return 0;
+    }
+    if CharClass::of(rope.char(idx - 1)) == CharClass::LineBreak {
+        return idx - 1;
+    }
+    while idx > 0 && CharClass::of(rope.char(idx - 1)) == CharClass::Whitespace {
+        idx -= 1;
+    }
+    if idx > 0 {
+        let class = CharClass::of(rope.char(idx - 1));
+        if class != CharClass::LineBreak {
+            while idx > 0 && CharClass::of(rope.char(idx - 1)) == class {
+                idx -= 1;
+            }
+        }
+    }
+    idx
+}
+
+/// 光标所在(或紧邻)的单词区间，不在单词上时返回 None
+pub fn word_range_at(rope: &Rope, char_idx: usize) -> Option<Range<usize>> {
+    let is_word = |idx: usize| {
+        matches!(
+            CharClass::of(rope.char(idx)),
+            CharClass::Word | CharClass::Cjk
+        )
+    };
+    let len = rope.len_chars();
+    let class_at = |idx: usize| CharClass::of(rope.char(idx));
+    // 优先取光标右侧的单词，其次左侧
+    let class = if char_idx < len && is_word(char_idx) {
+        class_at(char_idx)
+    } else if char_idx > 0 && is_word(char_idx - 1) {
+        class_at(char_idx - 1)
+    } else {
+        return None;
+    };
+    let mut start = char_idx;
+    while start > 0 && class_at(start - 1) == class {
+        start -= 1;
+    }
+    let mut end = char_idx;
+    while end < len && class_at(end) == class {
+        end += 1;
+    }
+    Some(start..end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::editor_new::Editor;
+
+    /// 从 from 开始反复调用 step，记录经过的边界直到不再移动
+    fn stops(text: &str, from: usize, step: fn(&Rope, usize) -> usize) -> Vec<usize> {
+        let rope = Rope::from_str(text);
+        let mut idx = from;
+        let mut stops = Vec::new();
+        loop {
+            let next = step(&rope, idx);
+            if next == idx {
+                return stops;
+            }
+            stops.push(next);
+            idx = next;
+        }
+    }
+
+    #[test]
+    fn char_classes() {
+        for (ch, class) in [
+            ('a', CharClass::Word),
+            ('_', CharClass::Word),
+            ('9', CharClass::Word),
+            ('中', CharClass::Cjk),
+            ('か', CharClass::Cjk),
+            ('한', CharClass::Cjk),
+            ('。', CharClass::Punctuation),
+            (':', CharClass::Punctuation),
+            (' ', CharClass::Whitespace),
+            ('\t', CharClass::Whitespace),
+            ('\n', CharClass::LineBreak),
+        ] {
+            assert_eq!(CharClass::of(ch), class, "{ch:?}");
+        }
+    }
+
+    #[test]
+    fn mixed_ascii_and_cjk() {
+        let text = "let x=中文abc;";
+        assert_eq!(stops(text, 0, next_word_boundary), [3, 5, 6, 8, 11, 12]);
+        assert_eq!(stops(text, 12, prev_word_boundary), [11, 8, 6, 5, 4, 0]);
+    }
+
+    #[test]
+    fn punctuation_runs() {
+        let text = "foo::bar(baz)";
+        assert_eq!(stops(text, 0, next_word_boundary), [3, 5, 8, 9, 12, 13]);
+        assert_eq!(stops(text, 13, prev_word_boundary), [12, 9, 8, 5, 3, 0]);
+    }
+
+    #[test]
+    fn line_breaks_are_their_own_stop() {
+        let text = "ab  \ncd";
+        assert_eq!(stops(text, 0, next_word_boundary), [2, 4, 5, 7]);
+        assert_eq!(stops(text, 7, prev_word_boundary), [5, 4, 0]);
+    }
+
+    #[test]
+    fn word_under_cursor() {
+        let rope = Rope::from_str("foo 中文.");
+        assert_eq!(word_range_at(&rope, 1), Some(0..3));
+        assert_eq!(word_range_at(&rope, 3), Some(0..3));
+        assert_eq!(word_range_at(&rope, 5), Some(4..6));
+        assert_eq!(word_range_at(&rope, 7), None);
+    }
+
+    #[test]
+    fn ctrl_arrows_and_word_deletion() {
+        let mut editor = Editor::with_text("a.b(中文) c");
+        let mut cols = Vec::new();
+        for _ in 0..7 {
+            editor.move_cursor_word_right(false);
+            cols.push(editor.get_cursor_position().col);
+        }
+        assert_eq!(cols, [1, 2, 3, 4, 6, 7, 9]);
+        editor.move_cursor_word_left(false);
+        editor.move_cursor_word_left(false);
+        assert_eq!(editor.get_cursor_position().col, 6);
+        editor.delete_word_backward();
+        assert_eq!(editor.get_text(), "a.b() c");
+        editor.delete_word_forward();
+        assert_eq!(editor.get_text(), "a.b( c");
+    }
+}
@@ -0,0 +1,59 @@
+use crate::praser::{bracket_pairs, BLOCK_COMMENT, LINE_COMMENT, STRING_DELIMITER};
+
+/// 语言的注释记号，没有单行注释的语言只能使用块注释
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CommentTokens {
+    pub line: Option<&'static str>,
+    pub block: (&'static str, &'static str),
+}
+
+/// 工具栏中可选的语言
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Language {
+    #[default]
+    Rust,
+    Markdown,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::Rust, Language::Markdown];
+
+    /// 工具栏中显示的名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::Rust => "Rust",
+            Language::Markdown => "Markdown",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "Markdown" => Language::Markdown,
+            _ => Language::Rust,
+        }
+    }
+
+    /// 输入时自动补全的成对字符 (开, 闭)
+    pub fn auto_pairs(&self) -> Vec<(char, char)> {
+        let mut pairs: Vec<(char, char)> = bracket_pairs().collect();
+        pairs.push((STRING_DELIMITER, STRING_DELIMITER));
+        if *self == Language::Markdown {
+            pairs.push(('`', '`'));
+        }
+        pairs
+    }
+
+    /// 切换注释时使用的记号，Rust 与语法高亮使用同一套
+    pub fn comment_tokens(&self) -> CommentTokens {
+        match self {
+            Language::Rust => CommentTokens {
+                line: Some(LINE_COMMENT),
+                block: BLOCK_COMMENT,
+            },
+            Language::Markdown => CommentTokens {
+                line: None,
+                block: ("<!--", "-->"),
+            },
+        }
+    }
+}
@@ -1,6 +1,7 @@
+mod language;
 mod praser;
 
-use components::{EditorArea, Toolbar};
+use components::{EditorArea, IndentConfig, KeymapProfile, Position, Toolbar};
 use dioxus::prelude::*;
 
 mod components;
@@ -12,14 +13,16 @@ fn main() {
 #[component]
 fn App() -> Element {
     let language = use_signal(|| "Rust".to_string());
-    let cursor_position = use_signal(|| (65, 8));
+    let cursor_position = use_signal(|| Position::new(65, 8));
+    let indent = use_signal(IndentConfig::default);
+    let keymap = use_signal(KeymapProfile::default);
     rsx! {
         div {
             style: "width: 100%; height: 100%; display: flex; flex-direction: column; overflow: hidden;",
 
-            Toolbar {language: language },
+            Toolbar {language: language, indent: indent, keymap: keymap },
 
-            EditorArea {language: language, cursor_position: cursor_position }
+            EditorArea {language: language, cursor_position: cursor_position, indent: indent, keymap: keymap }
         }
     }
 }
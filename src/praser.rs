@@ -59,9 +59,20 @@ pub enum TextNode {
 
 pub type SyntaxLine = SmallVec<[(SyntaxType, TextNode); 4]>;
 
+/// 可折叠的区域：折叠后保留 header 行，隐藏 header 之后直到 last 的各行
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FoldRegion {
+    /// 开括号或多行注释起始处的字符偏移量，用于标识区域
+    pub anchor: usize,
+    pub header: usize,
+    pub last: usize,
+}
+
 #[derive(Default)]
 pub struct SyntaxBlocks {
     blocks: FxHashMap<usize, SyntaxLine>,
+    /// 跨行的多行注释
+    comment_regions: Vec<FoldRegion>,
 }
 
 impl SyntaxBlocks {
@@ -79,6 +90,53 @@ impl SyntaxBlocks {
 
     pub fn clear(&mut self) {
         self.blocks.clear();
+        self.comment_regions.clear();
+    }
+
+    /// 括号 token 与其配对括号的位置(字符偏移量)；字符串和注释中的括号不是 token，不参与配对
+    pub fn bracket_pairs(&self, rope: &Rope) -> FxHashMap<usize, usize> {
+        let mut pairs = FxHashMap::default();
+        let mut stack: Vec<(usize, char)> = Vec::new();
+        for line in 0..self.len() {
+            for (syntax_type, text_node) in self.get_line(line) {
+                let (SyntaxType::Punctuation2, TextNode::Range(range)) = (syntax_type, text_node)
+                else {
+                    continue;
+                };
+                let ch = rope.char(range.start);
+                if is_open_bracket(ch) {
+                    stack.push((range.start, ch));
+                } else if let Some(open) = matching_bracket(ch) {
+                    // 不配对的闭括号直接忽略
+                    if stack.last().is_some_and(|(_, top)| *top == open) {
+                        let (start, _) = stack.pop().unwrap();
+                        pairs.insert(start, range.start);
+                        pairs.insert(range.start, start);
+                    }
+                }
+            }
+        }
+        pairs
+    }
+
+    /// 可折叠的区域(按 header 排序，每个 header 只保留最大的区域)：
+    /// 跨行的括号对隐藏到闭括号的上一行，多行注释隐藏到注释结束的行
+    pub fn fold_regions(&self, rope: &Rope) -> Vec<FoldRegion> {
+        let mut regions: Vec<FoldRegion> = self
+            .bracket_pairs(rope)
+            .into_iter()
+            .filter(|(open, close)| open < close)
+            .map(|(open, close)| FoldRegion {
+                anchor: open,
+                header: rope.char_to_line(open),
+                last: rope.char_to_line(close).saturating_sub(1),
+            })
+            .chain(self.comment_regions.iter().copied())
+            .filter(|region| region.last > region.header)
+            .collect();
+        regions.sort_by_key(|region| (region.header, std::cmp::Reverse(region.last)));
+        regions.dedup_by_key(|region| region.header);
+        regions
     }
 
     /// 统计前 col_index 个节点的字符总长度
@@ -113,7 +171,43 @@ const SPECIAL_KEYWORDS: &[&str] = &["self", "Self", "false", "true"];
 
 const SPECIAL_CHARACTER: &[char] = &['.', '=', ';', ':', '\'', ',', '#', '&', '-', '+', '^', '\\'];
 
-const SPECIAL_CHARACTER_2: &[char] = &['{', '}', '(', ')', '>', '<', '[', ']'];
+/// 括号，按 (开, 闭) 成对排列
+const SPECIAL_CHARACTER_2: &[char] = &['{', '}', '(', ')', '<', '>', '[', ']'];
+
+pub const STRING_DELIMITER: char = '"';
+
+/// 单行注释的起始记号
+pub const LINE_COMMENT: &str = "//";
+
+/// 多行注释的起止记号
+pub const BLOCK_COMMENT: (&str, &str) = ("/*", "*/");
+
+/// 括号的另一半
+pub fn matching_bracket(ch: char) -> Option<char> {
+    bracket_pairs().find_map(|(open, close)| {
+        if ch == open {
+            Some(close)
+        } else if ch == close {
+            Some(open)
+        } else {
+            None
+        }
+    })
+}
+
+/// 是否为开括号 `{`、`(`、`[`
+pub fn is_open_bracket(ch: char) -> bool {
+    bracket_pairs().any(|(open, _)| open == ch)
+}
+
+/// 成对的括号 (开, 闭)，取自 `SPECIAL_CHARACTER_2`；
+/// 尖括号也用作比较运算符，不参与配对
+pub fn bracket_pairs() -> impl Iterator<Item = (char, char)> {
+    SPECIAL_CHARACTER_2
+        .chunks(2)
+        .map(|pair| (pair[0], pair[1]))
+        .filter(|&pair| pair != ('<', '>'))
+}
 
 #[derive(PartialEq, Clone, Debug)]
 enum CommentTracking {
@@ -206,6 +300,7 @@ pub fn parse(rope: &Rope, syntax_blocks: &mut SyntaxBlocks) {
     // Track comments
     let mut tracking_comment = CommentTracking::None;
     let mut comment_stack: Option<Range<usize>> = None;
+    let mut comment_region: Option<FoldRegion> = None;
 
     // Track strings
     let mut tracking_string = false;
@@ -241,7 +336,7 @@ pub fn parse(rope: &Rope, syntax_blocks: &mut SyntaxBlocks) {
         }
 
         // Stop tracking a string
-        if tracking_string && ch == '"' {
+        if tracking_string && ch == STRING_DELIMITER {
             flush_generic_stack(rope, &mut generic_stack, &mut line, &mut last_semantic, ch);
 
             let mut st = string_stack.take().unwrap_or_default();
@@ -252,7 +347,7 @@ pub fn parse(rope: &Rope, syntax_blocks: &mut SyntaxBlocks) {
             tracking_string = false;
         }
         // Start tracking a string
-        else if tracking_comment == CommentTracking::None && ch == '"' {
+        else if tracking_comment == CommentTracking::None && ch == STRING_DELIMITER {
             string_stack = Some(i..i + 1);
             tracking_string = true;
         }
@@ -264,13 +359,18 @@ pub fn parse(rope: &Rope, syntax_blocks: &mut SyntaxBlocks) {
                 let current_comment: Cow<str> = rope.slice(ct.clone()).into();
 
                 // Stop a multi line comment
-                if ch == '/' && current_comment.ends_with("*/") {
+                if BLOCK_COMMENT.1.ends_with(ch) && current_comment.ends_with(BLOCK_COMMENT.1) {
                     generic_stack.take();
                     line.push((
                         SyntaxType::Comment,
                         TextNode::Range(comment_stack.take().unwrap()),
                     ));
                     tracking_comment = CommentTracking::None;
+
+                    if let Some(mut region) = comment_region.take() {
+                        region.last = syntax_blocks.len();
+                        syntax_blocks.comment_regions.push(region);
+                    }
                 }
             } else {
                 comment_stack = Some(i..i + 1);
@@ -305,19 +405,27 @@ pub fn parse(rope: &Rope, syntax_blocks: &mut SyntaxBlocks) {
         // Unknown (for now at least) characters
         else {
             // Start tracking a comment (both one line and multine)
-            if tracking_comment == CommentTracking::None && (ch == '*' || ch == '/') {
+            if tracking_comment == CommentTracking::None
+                && (LINE_COMMENT.ends_with(ch) || BLOCK_COMMENT.0.ends_with(ch))
+            {
                 if let Some(us) = generic_stack.as_mut() {
-                    let generic_stack_text: Cow<str> = rope.slice(us.clone()).into();
-                    if generic_stack_text == "/" {
+                    let mut generic_stack_text: String = rope.slice(us.clone()).into();
+                    generic_stack_text.push(ch);
+                    if generic_stack_text == LINE_COMMENT {
+                        tracking_comment = CommentTracking::OneLine
+                    } else if generic_stack_text == BLOCK_COMMENT.0 {
+                        tracking_comment = CommentTracking::MultiLine;
+                        comment_region = Some(FoldRegion {
+                            anchor: us.start,
+                            header: syntax_blocks.len(),
+                            last: syntax_blocks.len(),
+                        });
+                    }
+
+                    if tracking_comment != CommentTracking::None {
                         comment_stack = generic_stack.take();
 
                         push_to_stack(&mut comment_stack, i);
-
-                        if ch == '*' {
-                            tracking_comment = CommentTracking::MultiLine
-                        } else if ch == '/' {
-                            tracking_comment = CommentTracking::OneLine
-                        }
                     }
                 }
             }